@@ -0,0 +1,29 @@
+use scrypto::prelude::*;
+
+// Returns the buckets it receives, or keeps them.
+blueprint! {
+    struct Echo {
+        sunk: LazyMap<Address, Vault>,
+    }
+
+    impl Echo {
+        pub fn new() -> Component {
+            Self {
+                sunk: LazyMap::new(),
+            }
+            .instantiate()
+        }
+
+        pub fn echo(&self, bucket: Bucket) -> Bucket {
+            bucket
+        }
+
+        pub fn sink(&mut self, bucket: Bucket) {
+            let resource_address = bucket.resource_def().address();
+            match self.sunk.get(&resource_address) {
+                Some(mut vault) => vault.put(bucket),
+                None => self.sunk.insert(resource_address, Vault::with_bucket(bucket)),
+            }
+        }
+    }
+}
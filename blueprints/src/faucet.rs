@@ -0,0 +1,25 @@
+use scrypto::prelude::*;
+
+// Hands out a fixed supply of a new token to anyone.
+blueprint! {
+    struct Faucet {
+        tokens: Vault,
+    }
+
+    impl Faucet {
+        pub fn new(symbol: String, supply: Decimal) -> Component {
+            let tokens = ResourceBuilder::new_fungible(DIVISIBILITY_MAXIMUM)
+                .metadata("symbol", symbol)
+                .initial_supply_fungible(supply);
+
+            Self {
+                tokens: Vault::with_bucket(tokens),
+            }
+            .instantiate()
+        }
+
+        pub fn free(&mut self, amount: Decimal) -> Bucket {
+            self.tokens.take(amount)
+        }
+    }
+}
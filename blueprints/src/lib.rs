@@ -0,0 +1,9 @@
+//! Generic blueprints published by `scrypto_unit::TestEnv` on demand.
+
+mod echo;
+mod faucet;
+mod minter;
+mod mock;
+mod nft_minter;
+mod oracle;
+mod relay;
@@ -0,0 +1,51 @@
+use scrypto::prelude::*;
+
+// Mints a new token, either to anyone or only to the holder of the returned admin badge.
+blueprint! {
+    struct Minter {
+        minter_badge: Vault,
+        admin_badge: ResourceDef,
+        token: ResourceDef,
+        open: bool,
+    }
+
+    impl Minter {
+        pub fn new(symbol: String, open: bool) -> (Component, Bucket) {
+            let minter_badge = ResourceBuilder::new_fungible(DIVISIBILITY_NONE)
+                .metadata("name", "Minter Badge")
+                .initial_supply_fungible(1);
+            let admin_badge = ResourceBuilder::new_fungible(DIVISIBILITY_NONE)
+                .metadata("name", "Admin Badge")
+                .initial_supply_fungible(1);
+            let token = ResourceBuilder::new_fungible(DIVISIBILITY_MAXIMUM)
+                .metadata("symbol", symbol)
+                .flags(MINTABLE | BURNABLE)
+                .badge(minter_badge.resource_def(), MAY_MINT | MAY_BURN)
+                .no_initial_supply();
+
+            let component = Self {
+                minter_badge: Vault::with_bucket(minter_badge),
+                admin_badge: admin_badge.resource_def(),
+                token,
+                open,
+            }
+            .instantiate();
+
+            (component, admin_badge)
+        }
+
+        pub fn mint(&mut self, amount: Decimal) -> Bucket {
+            assert!(self.open, "Minting requires the admin badge");
+            self.minter_badge.authorize(|badge| self.token.mint(amount, badge))
+        }
+
+        #[auth(admin_badge)]
+        pub fn mint_as_admin(&mut self, amount: Decimal) -> Bucket {
+            self.minter_badge.authorize(|badge| self.token.mint(amount, badge))
+        }
+
+        pub fn burn(&mut self, tokens: Bucket) {
+            self.minter_badge.authorize(|badge| tokens.burn_with_auth(badge))
+        }
+    }
+}
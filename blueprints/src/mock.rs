@@ -0,0 +1,90 @@
+use scrypto::abi::{Function, Method, Mutability};
+use scrypto::buffer::*;
+use scrypto::kernel::*;
+use scrypto::prelude::*;
+
+/// A component whose methods return the SBOR-encoded responses set by the test.
+///
+/// The dispatcher is written by hand instead of with `blueprint!` so that any method name
+/// can be called, the response being looked up by method name, or by method name and
+/// arguments first.
+#[derive(TypeId, Encode, Decode)]
+struct Mock {
+    responses: HashMap<String, Vec<u8>>,
+    calls: Vec<(String, Vec<Vec<u8>>)>,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+#[no_mangle]
+pub extern "C" fn Mock_main() -> *mut u8 {
+    let calldata: GetCallDataOutput = call_kernel(GET_CALL_DATA, GetCallDataInput {});
+
+    let rtn = match calldata.function.as_str() {
+        "new" => {
+            let state = Mock {
+                responses: HashMap::new(),
+                calls: Vec::new(),
+            };
+            scrypto_encode(&Component::new("Mock", state))
+        }
+        method => {
+            let address: Address = scrypto_decode(&calldata.args[0]).unwrap();
+            let component = Component::from(address);
+            let mut state: Mock = component.get_state();
+            let args = calldata.args[1..].to_vec();
+
+            let rtn = match method {
+                "set_response" => {
+                    let key: String = scrypto_decode(&args[0]).unwrap();
+                    let value: String = scrypto_decode(&args[1]).unwrap();
+                    state.responses.insert(key, from_hex(&value));
+                    scrypto_encode(&())
+                }
+                _ => {
+                    let key = format!("{}:{}", method, to_hex(&scrypto_encode(&args)));
+                    let rtn = match state
+                        .responses
+                        .get(&key)
+                        .or_else(|| state.responses.get(method))
+                    {
+                        Some(rtn) => rtn.clone(),
+                        None => panic!("No response mocked for {}", method),
+                    };
+                    state.calls.push((method.to_owned(), args));
+                    rtn
+                }
+            };
+            component.put_state(state);
+            rtn
+        }
+    };
+
+    scrypto_wrap(rtn)
+}
+
+#[no_mangle]
+pub extern "C" fn Mock_abi() -> *mut u8 {
+    let functions = vec![Function {
+        name: "new".to_owned(),
+        inputs: vec![],
+        output: Component::describe(),
+    }];
+    let methods = vec![Method {
+        name: "set_response".to_owned(),
+        mutability: Mutability::Mutable,
+        inputs: vec![String::describe(), String::describe()],
+        output: <()>::describe(),
+    }];
+
+    scrypto_wrap(scrypto_encode_for_kernel(&(functions, methods)))
+}
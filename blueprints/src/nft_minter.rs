@@ -0,0 +1,48 @@
+use scrypto::prelude::*;
+
+/// The data of the non-fungibles minted by `NftMinter`.
+#[derive(NonFungibleData)]
+pub struct Nft {
+    name: String,
+}
+
+// Mints non-fungibles with sequential keys to anyone.
+blueprint! {
+    struct NftMinter {
+        minter_badge: Vault,
+        nft: ResourceDef,
+        next_id: u128,
+    }
+
+    impl NftMinter {
+        pub fn new(name: String) -> Component {
+            let minter_badge = ResourceBuilder::new_fungible(DIVISIBILITY_NONE)
+                .metadata("name", "NFT Minter Badge")
+                .initial_supply_fungible(1);
+            let nft = ResourceBuilder::new_non_fungible()
+                .metadata("name", name)
+                .flags(MINTABLE | BURNABLE)
+                .badge(minter_badge.resource_def(), MAY_MINT | MAY_BURN)
+                .no_initial_supply();
+
+            Self {
+                minter_badge: Vault::with_bucket(minter_badge),
+                nft,
+                next_id: 1,
+            }
+            .instantiate()
+        }
+
+        pub fn mint(&mut self, name: String) -> Bucket {
+            let key = NonFungibleKey::from(self.next_id);
+            self.next_id += 1;
+            self.minter_badge.authorize(|badge| {
+                self.nft.mint_non_fungible(&key, Nft { name }, badge)
+            })
+        }
+
+        pub fn burn(&mut self, nfts: Bucket) {
+            self.minter_badge.authorize(|badge| nfts.burn_with_auth(badge))
+        }
+    }
+}
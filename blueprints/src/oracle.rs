@@ -0,0 +1,22 @@
+use scrypto::prelude::*;
+
+// Reports a price that anyone can set.
+blueprint! {
+    struct Oracle {
+        price: Decimal,
+    }
+
+    impl Oracle {
+        pub fn new(price: Decimal) -> Component {
+            Self { price }.instantiate()
+        }
+
+        pub fn get_price(&self) -> Decimal {
+            self.price
+        }
+
+        pub fn set_price(&mut self, price: Decimal) {
+            self.price = price;
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use scrypto::prelude::*;
+
+// Calls a method of another component, so it can be tested as the dependency of a blueprint.
+blueprint! {
+    struct Relay {}
+
+    impl Relay {
+        pub fn new() -> Component {
+            Self {}.instantiate()
+        }
+
+        pub fn get_decimal(&self, component: Address, method: String) -> Decimal {
+            Component::from(component).call(&method, vec![])
+        }
+
+        pub fn get_decimal_for(
+            &self,
+            component: Address,
+            method: String,
+            arg: Decimal,
+        ) -> Decimal {
+            Component::from(component).call(&method, args![arg])
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use std::fmt;
+use std::time::Duration;
+
+use radix_engine::ledger::SubstateStore;
+
+use crate::fixture::Fixture;
+use crate::{ExecutionMetrics, MeteredSubstateStore, TestEnv};
+
+/// The measurements of a benchmarked call.
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    /// The label of the benchmark.
+    pub label: String,
+    /// The metrics of each iteration.
+    pub samples: Vec<ExecutionMetrics>,
+}
+
+impl BenchResult {
+    fn sorted_elapsed(&self) -> Vec<Duration> {
+        let mut elapsed: Vec<Duration> = self.samples.iter().map(|m| m.elapsed).collect();
+        elapsed.sort();
+        elapsed
+    }
+
+    /// Returns the fastest iteration's executor time.
+    pub fn min(&self) -> Duration {
+        self.sorted_elapsed()[0]
+    }
+
+    /// Returns the median executor time.
+    pub fn median(&self) -> Duration {
+        let elapsed = self.sorted_elapsed();
+        elapsed[elapsed.len() / 2]
+    }
+
+    /// Returns the slowest iteration's executor time.
+    pub fn max(&self) -> Duration {
+        *self.sorted_elapsed().last().unwrap()
+    }
+
+    /// Returns the metered cost of an iteration, which is the same for every iteration
+    /// of a deterministic call.
+    pub fn cost(&self) -> ExecutionMetrics {
+        self.samples[0]
+    }
+}
+
+fn counter(value: Option<u64>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => String::from("-"),
+    }
+}
+
+impl fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cost = self.cost();
+        write!(
+            f,
+            "{}: {} iterations, min {:?}, median {:?}, max {:?}, {} reads, {} writes, {} bytes written",
+            self.label,
+            self.samples.len(),
+            self.min(),
+            self.median(),
+            self.max(),
+            counter(cost.substate_reads),
+            counter(cost.substate_writes),
+            counter(cost.bytes_written)
+        )
+    }
+}
+
+/// The results of the same call benchmarked against two setups.
+#[derive(Debug, Clone)]
+pub struct BenchComparison {
+    pub baseline: BenchResult,
+    pub candidate: BenchResult,
+}
+
+impl fmt::Display for BenchComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.baseline)?;
+        writeln!(f, "{}", self.candidate)?;
+        let ratio =
+            self.candidate.median().as_secs_f64() / self.baseline.median().as_secs_f64();
+        write!(f, "median time ratio: {:.2}", ratio)
+    }
+}
+
+/// A benchmark harness running a call many times, each time against a freshly set up
+/// test environment so state does not drift between iterations.
+///
+/// The ledger is metered, so results include substate accesses as well as executor time.
+///
+/// # Examples
+/// ```
+/// use scrypto_unit::*;
+/// use radix_engine::ledger::*;
+/// use scrypto::prelude::*;
+///
+/// let bench = Bench::new(InMemorySubstateStore::with_bootstrap, |env| {
+///     env.create_user("acc1");
+///     env.publish_package(
+///         "package",
+///         include_code!("../tests/assets/hello-world", "hello_world")
+///     );
+///     env.call_function("Hello", "new", vec!["1".to_owned()]);
+/// });
+///
+/// let result = bench.run("update_state", 10, |env| {
+///     env.call("update_state", vec!["2".to_owned()]);
+/// });
+/// println!("{}", result);
+/// ```
+pub struct Bench<L: SubstateStore> {
+    fixture: Fixture<MeteredSubstateStore<L>>,
+}
+
+impl<L: SubstateStore> Bench<L> {
+    /// Returns a benchmark harness.
+    ///
+    /// # Arguments
+    ///
+    /// * `ledger` - Creates the fresh ledger of each iteration.
+    /// * `setup` - Prepares the test environment of each iteration, untimed.
+    pub fn new<M, S>(ledger: M, setup: S) -> Self
+    where
+        M: Fn() -> L + 'static,
+        S: Fn(&mut TestEnv<'_, MeteredSubstateStore<L>>) + 'static,
+    {
+        Self {
+            fixture: Fixture::new(move || MeteredSubstateStore::new(ledger()), setup),
+        }
+    }
+
+    /// Runs a call the given number of times and measures the transactions it executes.
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - The label of the benchmark, used in reports.
+    /// * `iterations` - The number of times to run the call.
+    /// * `call` - The call to benchmark.
+    pub fn run<F>(&self, label: &str, iterations: usize, call: F) -> BenchResult
+    where
+        F: Fn(&mut TestEnv<'_, MeteredSubstateStore<L>>),
+    {
+        assert!(iterations > 0, "Cannot benchmark zero iterations");
+
+        let samples = (0..iterations)
+            .map(|_| {
+                let mut ledger = self.fixture.ledger();
+                let mut env = TestEnv::new_metered(&mut ledger);
+                self.fixture.setup(&mut env);
+
+                let (_, metrics) = env.measure(|env| call(env));
+                metrics
+            })
+            .collect();
+
+        BenchResult {
+            label: String::from(label),
+            samples,
+        }
+    }
+
+    /// Runs the same call against this harness and another one, e.g. one publishing a
+    /// different version of a package under the same name.
+    ///
+    /// # Arguments
+    ///
+    /// * `candidate` - The harness to compare with.
+    /// * `iterations` - The number of times to run the call on each harness.
+    /// * `call` - The call to benchmark.
+    pub fn compare<F>(
+        &self,
+        candidate: &Bench<L>,
+        iterations: usize,
+        call: F,
+    ) -> BenchComparison
+    where
+        F: Fn(&mut TestEnv<'_, MeteredSubstateStore<L>>),
+    {
+        BenchComparison {
+            baseline: self.run("baseline", iterations, &call),
+            candidate: candidate.run("candidate", iterations, &call),
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use radix_engine::ledger::SubstateStore;
+use radix_engine::transaction::*;
+use scrypto::prelude::*;
+
+use crate::{TestEnv, ToAddress, TransactionKind};
+
+/// The name under which the generic blueprints shipped with this crate are published.
+///
+/// Besides `Mock`, the package contains the fixtures instantiated by `create_faucet`,
+/// `create_minter`, `create_nft_minter`, `create_echo`, `create_oracle` and `create_relay`.
+pub const BLUEPRINTS_PACKAGE: &str = "scrypto_unit";
+
+#[cfg(feature = "blueprints")]
+fn code() -> &'static [u8] {
+    include_code!("../blueprints", "scrypto_unit_blueprints")
+}
+
+#[cfg(not(feature = "blueprints"))]
+fn code() -> &'static [u8] {
+    panic!("The generic blueprints require the \"blueprints\" feature of scrypto-unit")
+}
+
+impl<'a, L: SubstateStore> TestEnv<'a, L> {
+    /// Returns the package of generic blueprints shipped with this crate, publishing it
+    /// under `BLUEPRINTS_PACKAGE` on first use.
+    ///
+    /// Unlike `publish_package`, the package never becomes the current package.
+    ///
+    /// # Panics
+    ///
+    /// When the crate is built without the `blueprints` feature.
+    pub fn blueprints_package(&mut self) -> Address {
+        if let Some(package) = self.packages.get(BLUEPRINTS_PACKAGE) {
+            return *package;
+        }
+
+        let transaction = TransactionBuilder::new(&self.executor)
+            .publish_package(code())
+            .build(Vec::new())
+            .unwrap();
+        let receipt = self.run(TransactionKind::PublishPackage, transaction);
+        let package = match receipt.package(0) {
+            Some(package) => package,
+            None => panic!(
+                "Failed to publish the generic blueprints:\n{}",
+                self.display(&receipt)
+            ),
+        };
+        self.packages.insert(String::from(BLUEPRINTS_PACKAGE), package);
+
+        package
+    }
+
+    fn create_fixture(&mut self, blueprint: &str, params: Vec<String>) -> Address {
+        self.blueprints_package();
+        let receipt = self.with_package(BLUEPRINTS_PACKAGE, |env| {
+            env.call_function(blueprint, "new", params)
+        });
+        match receipt.component(0) {
+            Some(component) => component,
+            None => panic!(
+                "Failed to instantiate {}:\n{}",
+                blueprint,
+                self.display(&receipt)
+            ),
+        }
+    }
+
+    /// Instantiates a `Faucet` holding the supply of a new token, handed out by its
+    /// `free(amount)` method.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The symbol of the token, under which it is registered.
+    /// * `supply` - The supply of the token.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    /// env.create_user("acc1");
+    ///
+    /// let faucet = env.create_faucet("GUM", 1000.into());
+    /// env.call_method(faucet, "free", vec!["10".to_owned()]);
+    /// assert_eq!(env.get_amount_for_rd("acc1", "GUM"), 10.into());
+    /// ```
+    pub fn create_faucet(&mut self, symbol: &str, supply: Decimal) -> Address {
+        self.create_fixture("Faucet", vec![symbol.to_owned(), supply.to_string()])
+    }
+
+    /// Instantiates a `Minter` of a new token and deposits its admin badge to the current
+    /// user.
+    ///
+    /// The `mint_as_admin(amount)` method requires the admin badge, while `mint(amount)`
+    /// only succeeds when the minter is open. Both are burnt with `burn(tokens)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The symbol of the token, under which it is registered.
+    /// * `open` - Whether anyone can mint.
+    pub fn create_minter(&mut self, symbol: &str, open: bool) -> Address {
+        self.create_fixture("Minter", vec![symbol.to_owned(), open.to_string()])
+    }
+
+    /// Instantiates an `NftMinter` of a new non-fungible resource, minted with sequential
+    /// keys by its `mint(name)` method.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the non-fungible resource.
+    pub fn create_nft_minter(&mut self, name: &str) -> Address {
+        self.create_fixture("NftMinter", vec![name.to_owned()])
+    }
+
+    /// Instantiates an `Echo`, whose `echo(bucket)` method returns the bucket it receives
+    /// and whose `sink(bucket)` method keeps it.
+    pub fn create_echo(&mut self) -> Address {
+        self.create_fixture("Echo", Vec::new())
+    }
+
+    /// Instantiates an `Oracle` reporting a price through its `get_price()` method.
+    ///
+    /// # Arguments
+    ///
+    /// * `price` - The initial price, changed with `set_oracle_price`.
+    pub fn create_oracle(&mut self, price: Decimal) -> Address {
+        self.create_fixture("Oracle", vec![price.to_string()])
+    }
+
+    /// Instantiates a `Relay`, whose `get_decimal(component, method)` and
+    /// `get_decimal_for(component, method, arg)` methods call a method of another component
+    /// returning a `Decimal`, e.g. a mock.
+    pub fn create_relay(&mut self) -> Address {
+        self.create_fixture("Relay", Vec::new())
+    }
+
+    /// Sets the price reported by an `Oracle`.
+    ///
+    /// # Arguments
+    ///
+    /// * `oracle` - The address or registered name of the oracle.
+    /// * `price` - The new price.
+    pub fn set_oracle_price(&mut self, oracle: impl ToAddress, price: Decimal) -> &mut Self {
+        let receipt = self.call_method(oracle, "set_price", vec![price.to_string()]);
+        if receipt.result.is_err() {
+            panic!("Failed to set the oracle price:\n{}", self.display(&receipt));
+        }
+
+        self
+    }
+}
@@ -0,0 +1,294 @@
+use radix_engine::ledger::SubstateStore;
+use scrypto::prelude::*;
+
+use crate::{FrameCall, TestEnv, ToAddress, Trace, TransactionRecord};
+
+/// Asserts that a transaction invoked a component, optionally a given method of it and a
+/// given number of times.
+///
+/// The transaction is the last one of a `TestEnv`, a `TransactionRecord` or a `Trace`, see
+/// `CallSource`. Components are given by address, or by registered name for a `TestEnv`.
+///
+/// Calls made from within a blueprint are only observed through the state and holdings they
+/// change, see `Trace`, which limits what can be asserted:
+///
+/// * Methods are only known for the calls made by instructions. Asserting on a method of a
+///   component only invoked from within a blueprint, such as a pool called by a router,
+///   fails with "method of nested call unknown"; assert on the component alone instead.
+/// * A nested call that changes nothing, such as reading an oracle, is not seen at all.
+///   `assert_not_called!` therefore fails, rather than passing, for a component no
+///   instruction called when the transaction called a blueprint that could have.
+///
+/// # Examples
+/// ```
+/// use scrypto_unit::*;
+/// use radix_engine::ledger::*;
+/// use scrypto::prelude::*;
+///
+/// let mut ledger = InMemorySubstateStore::with_bootstrap();
+/// let mut env = TestEnv::new_with_tracing(&mut ledger);
+/// env.create_user("acc1");
+/// env.create_user("acc2");
+/// let acc2 = *env.get_user("acc2");
+/// env.acting_as("acc1");
+/// env.transfer_resource(10.into(), &RADIX_TOKEN, &acc2);
+///
+/// assert_called!(env, "acc1", "withdraw", 1);
+/// assert_called!(env, "acc2");
+/// assert_not_called!(env, "acc2", "withdraw");
+///
+/// let record = env.history.last().unwrap();
+/// assert_called!(record, acc2.account);
+/// ```
+#[macro_export]
+macro_rules! assert_called {
+    (@check $source:expr, $component:expr, $method:expr, $times:expr) => {{
+        use $crate::{CallSource, CallTarget};
+        let source = &$source;
+        let component = source.call_target($component);
+        let trace = source.call_trace().unwrap_or_else(|message| panic!("{}", message));
+        if let Err(message) = trace.check_called(component, $method, $times) {
+            panic!("{}\n{}", message, trace);
+        }
+    }};
+    ($source:expr, $component:expr) => {
+        $crate::assert_called!(@check $source, $component, None, None)
+    };
+    ($source:expr, $component:expr, $method:expr) => {
+        $crate::assert_called!(@check $source, $component, Some($method), None)
+    };
+    ($source:expr, $component:expr, $method:expr, $times:expr) => {
+        $crate::assert_called!(@check $source, $component, Some($method), Some($times))
+    };
+}
+
+/// Asserts that a transaction did not invoke a component, or a given method of it.
+///
+/// Fails when a call to the component could have gone unobserved, see `assert_called!` for
+/// the limits and an example.
+#[macro_export]
+macro_rules! assert_not_called {
+    ($source:expr, $component:expr) => {
+        $crate::assert_called!(@check $source, $component, None, Some(0))
+    };
+    ($source:expr, $component:expr, $method:expr) => {
+        $crate::assert_called!(@check $source, $component, Some($method), Some(0))
+    };
+}
+
+/// Asserts that a transaction invoked no component besides the given ones, the user
+/// accounts and the treasury.
+///
+/// The transaction is given as for `assert_called!`.
+///
+/// # Examples
+/// ```
+/// use scrypto_unit::*;
+/// use radix_engine::ledger::*;
+/// use scrypto::prelude::*;
+///
+/// let mut ledger = InMemorySubstateStore::with_bootstrap();
+/// let mut env = TestEnv::new_with_tracing(&mut ledger);
+/// env.create_user("acc1");
+/// env.create_token(10000.into());
+///
+/// assert_only_called!(env);
+/// assert_only_called!(env.last_trace().unwrap());
+/// ```
+#[macro_export]
+macro_rules! assert_only_called {
+    ($source:expr $(, $component:expr)* $(,)?) => {{
+        #[allow(unused_imports)]
+        use $crate::{CallSource, CallTarget};
+        let source = &$source;
+        let expected = vec![$(source.call_target($component)),*];
+        let trace = source.call_trace().unwrap_or_else(|message| panic!("{}", message));
+        if let Err(message) = trace.check_only_called(&expected) {
+            panic!("{}\n{}", message, trace);
+        }
+    }};
+}
+
+/// A transaction the call assertions can be made against: the last transaction of a
+/// `TestEnv`, a `TransactionRecord` or a `Trace`.
+pub trait CallSource {
+    /// Returns the trace of the transaction, or why there is none.
+    fn call_trace(&self) -> Result<&Trace, String>;
+}
+
+/// Resolves the components given to the call assertions, see `CallSource`.
+pub trait CallTarget<C> {
+    /// Returns the address of a component.
+    fn call_target(&self, component: C) -> Address;
+}
+
+const NO_TRACE: &str = "No trace captured, use TestEnv::new_with_tracing";
+
+impl<'a, L: SubstateStore> CallSource for TestEnv<'a, L> {
+    fn call_trace(&self) -> Result<&Trace, String> {
+        self.last_trace().ok_or_else(|| NO_TRACE.to_owned())
+    }
+}
+
+impl<'a, L: SubstateStore, C: ToAddress> CallTarget<C> for TestEnv<'a, L> {
+    fn call_target(&self, component: C) -> Address {
+        component.to_address(self)
+    }
+}
+
+impl CallSource for TransactionRecord {
+    fn call_trace(&self) -> Result<&Trace, String> {
+        self.trace.as_ref().ok_or_else(|| NO_TRACE.to_owned())
+    }
+}
+
+impl CallTarget<Address> for TransactionRecord {
+    fn call_target(&self, component: Address) -> Address {
+        component
+    }
+}
+
+impl CallSource for Trace {
+    fn call_trace(&self) -> Result<&Trace, String> {
+        Ok(self)
+    }
+}
+
+impl CallTarget<Address> for Trace {
+    fn call_target(&self, component: Address) -> Address {
+        component
+    }
+}
+
+impl Trace {
+    /// Returns how many times the transaction invoked a component, or a given method of it.
+    ///
+    /// # Arguments
+    ///
+    /// * `component` - The address of the component.
+    /// * `method` - The method, or `None` to count every call including inferred ones.
+    pub fn call_count(&self, component: Address, method: Option<&str>) -> usize {
+        self.calls(component, method).len()
+    }
+
+    /// Checks how many times the transaction invoked a component, or a given method of it.
+    ///
+    /// Checking a method of a component only invoked from within blueprints fails, as the
+    /// methods of those calls are unknown. Checking that a component no instruction called
+    /// was not called fails when `may_hide_calls`, as calls changing nothing are not seen.
+    ///
+    /// # Arguments
+    ///
+    /// * `component` - The address of the component.
+    /// * `method` - The method, or `None` to count every call including inferred ones.
+    /// * `times` - The expected number of calls, or `None` for at least one.
+    pub fn check_called(
+        &self,
+        component: Address,
+        method: Option<&str>,
+        times: Option<usize>,
+    ) -> Result<(), String> {
+        let target = match method {
+            Some(method) => format!("{}.{}", self.name(&component), method),
+            None => self.name(&component),
+        };
+
+        let frames = self.calls(component, None);
+        let inferred_only =
+            !frames.is_empty() && frames.iter().all(|frame| frame.name().is_none());
+        if method.is_some() && inferred_only {
+            return Err(format!("{}: method of nested call unknown", target));
+        }
+
+        let direct = frames
+            .iter()
+            .any(|frame| matches!(frame.call, FrameCall::Method { .. }));
+        if times == Some(0) && !direct && self.may_hide_calls() {
+            return Err(format!(
+                "{}: not called by an instruction, and nested calls that change no state cannot be observed",
+                target
+            ));
+        }
+
+        let count = self.call_count(component, method);
+        match times {
+            None if count == 0 => Err(format!("Expected a call to {}, found none", target)),
+            Some(times) if count != times => Err(format!(
+                "Expected {} call(s) to {}, found {}",
+                times, target, count
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns whether the transaction may have made calls the trace does not show: whether
+    /// an instruction called a blueprint function or a component other than an account.
+    pub fn may_hide_calls(&self) -> bool {
+        self.frames.iter().any(|frame| match &frame.call {
+            FrameCall::Function { .. } => true,
+            FrameCall::Method { component, .. } => !self.accounts.contains(component),
+            FrameCall::Inferred { .. } => false,
+        })
+    }
+
+    /// Checks that the transaction invoked no component besides the given ones, the user
+    /// accounts and the treasury.
+    pub fn check_only_called(&self, components: &[Address]) -> Result<(), String> {
+        let unexpected: Vec<String> = self
+            .touched()
+            .into_iter()
+            .filter(|component| !components.contains(component))
+            .filter(|component| !self.accounts.contains(component))
+            .map(|component| self.name(&component))
+            .collect();
+
+        match unexpected.is_empty() {
+            true => Ok(()),
+            false => Err(format!("Unexpected calls to {}", unexpected.join(", "))),
+        }
+    }
+}
+
+impl<'a, L: SubstateStore> TestEnv<'a, L> {
+    fn traced(&self) -> &Trace {
+        match self.last_trace() {
+            Some(trace) => trace,
+            None => panic!("{}", NO_TRACE),
+        }
+    }
+
+    /// Returns how many times the last transaction invoked a component, or a given method
+    /// of it.
+    ///
+    /// # Arguments
+    ///
+    /// * `component` - The address or registered name of the component.
+    /// * `method` - The method, or `None` to count every call including inferred ones.
+    pub fn call_count(&self, component: impl ToAddress, method: Option<&str>) -> usize {
+        self.traced().call_count(component.to_address(self), method)
+    }
+
+    /// Checks how many times the last transaction invoked a component, or a given method
+    /// of it, see `Trace::check_called`.
+    ///
+    /// # Arguments
+    ///
+    /// * `component` - The address or registered name of the component.
+    /// * `method` - The method, or `None` to count every call including inferred ones.
+    /// * `times` - The expected number of calls, or `None` for at least one.
+    pub fn check_called(
+        &self,
+        component: impl ToAddress,
+        method: Option<&str>,
+        times: Option<usize>,
+    ) -> Result<(), String> {
+        self.traced()
+            .check_called(component.to_address(self), method, times)
+    }
+
+    /// Checks that the last transaction invoked no component besides the given ones, the user
+    /// accounts and the treasury.
+    pub fn check_only_called(&self, components: &[Address]) -> Result<(), String> {
+        self.traced().check_only_called(components)
+    }
+}
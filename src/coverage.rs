@@ -0,0 +1,259 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use radix_engine::ledger::SubstateStore;
+use radix_engine::transaction::*;
+use scrypto::prelude::*;
+
+use crate::{Call, TestEnv, TransactionKind};
+
+/// The environment variable naming the directory test environments write coverage to.
+pub const COVERAGE_VAR: &str = "SCRYPTO_UNIT_COVERAGE";
+
+static COVERAGE_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// A function or method of a blueprint, as `(package, blueprint, name)`.
+pub type CoverageKey = (String, String, String);
+
+/// How often each blueprint function and method was invoked, successfully or not.
+///
+/// Entries of the ABI of every invoked blueprint are included, so those never invoked
+/// show up with zero calls. Only the calls made by transaction instructions are counted,
+/// see `TestEnv::coverage`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    /// The number of successful and failed calls of each function and method.
+    pub entries: BTreeMap<CoverageKey, (u64, u64)>,
+}
+
+impl Coverage {
+    /// Returns empty coverage.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the calls counted in another coverage to this one.
+    pub fn merge(&mut self, other: &Coverage) {
+        for (key, (successes, failures)) in &other.entries {
+            let entry = self.entries.entry(key.clone()).or_insert((0, 0));
+            entry.0 += successes;
+            entry.1 += failures;
+        }
+    }
+
+    /// Returns the functions and methods never invoked.
+    pub fn uncovered(&self) -> Vec<&CoverageKey> {
+        self.entries
+            .iter()
+            .filter(|(_, (successes, failures))| successes + failures == 0)
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    /// Writes the coverage to a uniquely named file in a directory, so coverage of tests
+    /// running in parallel can be collected afterwards.
+    pub fn write_to_dir<P: AsRef<Path>>(&self, dir: P) -> io::Result<()> {
+        fs::create_dir_all(dir.as_ref())?;
+        let file = format!(
+            "{}-{}.cov",
+            std::process::id(),
+            COVERAGE_FILES.fetch_add(1, Ordering::SeqCst)
+        );
+
+        let mut text = String::new();
+        for ((package, blueprint, name), (successes, failures)) in &self.entries {
+            text.push_str(&format!(
+                "{}\t{}\t{}\t{}\t{}\n",
+                package, blueprint, name, successes, failures
+            ));
+        }
+        fs::write(dir.as_ref().join(file), text)
+    }
+
+    /// Merges the coverage files written to a directory by `write_to_dir`.
+    pub fn collect<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let mut coverage = Coverage::new();
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(true, |extension| extension != "cov") {
+                continue;
+            }
+            for line in fs::read_to_string(&path)?.lines() {
+                let fields: Vec<&str> = line.split('\t').collect();
+                let counts = match fields.as_slice() {
+                    [_, _, _, successes, failures] => {
+                        (successes.parse::<u64>().ok(), failures.parse::<u64>().ok())
+                    }
+                    _ => (None, None),
+                };
+                match counts {
+                    (Some(successes), Some(failures)) => {
+                        let key = (
+                            fields[0].to_owned(),
+                            fields[1].to_owned(),
+                            fields[2].to_owned(),
+                        );
+                        let entry = coverage.entries.entry(key).or_insert((0, 0));
+                        entry.0 += successes;
+                        entry.1 += failures;
+                    }
+                    _ => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("Invalid coverage line {:?} in {:?}", line, path),
+                        ))
+                    }
+                }
+            }
+        }
+
+        Ok(coverage)
+    }
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let covered = self.entries.len() - self.uncovered().len();
+        writeln!(
+            f,
+            "Blueprint coverage: {}/{} functions and methods invoked",
+            covered,
+            self.entries.len()
+        )?;
+        for ((package, blueprint, name), (successes, failures)) in &self.entries {
+            let marker = if successes + failures == 0 { "!!" } else { "  " };
+            writeln!(
+                f,
+                "{} {}::{}::{} ({} ok, {} failed)",
+                marker, package, blueprint, name, successes, failures
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, L: SubstateStore> TestEnv<'a, L> {
+    /// Returns the coverage of the functions and methods of registered packages by the
+    /// transactions executed so far.
+    ///
+    /// Only the functions and methods called by transaction instructions are counted, as
+    /// calls made from within blueprints are not recorded. A blueprint only reached through
+    /// another component, such as a pool called by a router, shows up as uncovered, or not
+    /// at all if no instruction invoked it.
+    ///
+    /// # Examples
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    /// env.create_user("acc1");
+    /// env.publish_package(
+    ///     "package",
+    ///     include_code!("../tests/assets/hello-world", "hello_world")
+    /// );
+    /// env.call_function("Hello", "new", vec!["1".to_owned()]);
+    ///
+    /// let coverage = env.coverage();
+    /// let uncovered = coverage.uncovered();
+    /// assert_eq!(uncovered.len(), 1);
+    /// assert_eq!(uncovered[0].2, "update_state");
+    /// ```
+    pub fn coverage(&self) -> Coverage {
+        let mut coverage = Coverage::new();
+        let mut blueprints = BTreeSet::new();
+
+        let records = self
+            .history
+            .iter()
+            .filter(|record| record.kind != TransactionKind::MockResponse);
+        for record in records {
+            for call in &record.calls {
+                let (package, blueprint, name) = match call {
+                    Call::Function {
+                        package,
+                        blueprint,
+                        function,
+                    } => (*package, blueprint.clone(), function.clone()),
+                    Call::Method { component, method } => {
+                        match self.executor.ledger().get_component(*component) {
+                            Some(c) => (
+                                c.package_address(),
+                                c.blueprint_name().to_owned(),
+                                method.clone(),
+                            ),
+                            None => continue,
+                        }
+                    }
+                };
+                let package_name = match Self::stable_name(&self.packages, &package) {
+                    Some(package_name) => package_name,
+                    None => continue,
+                };
+
+                blueprints.insert((package_name.clone(), package, blueprint.clone()));
+                let entry = coverage
+                    .entries
+                    .entry((package_name, blueprint, name))
+                    .or_insert((0, 0));
+                match record.is_success() {
+                    true => entry.0 += 1,
+                    false => entry.1 += 1,
+                }
+            }
+        }
+
+        for (package_name, package, blueprint) in blueprints {
+            if let Ok(abi) = self.executor.export_abi(package, &blueprint) {
+                let names = abi
+                    .functions
+                    .iter()
+                    .map(|function| function.name.clone())
+                    .chain(abi.methods.iter().map(|method| method.name.clone()));
+                for name in names {
+                    coverage
+                        .entries
+                        .entry((package_name.clone(), blueprint.clone(), name))
+                        .or_insert((0, 0));
+                }
+            }
+        }
+
+        coverage
+    }
+
+    /// Writes the coverage of this test environment to the directory named by
+    /// `SCRYPTO_UNIT_COVERAGE`, if set, to be merged with `Coverage::collect`.
+    ///
+    /// Call it at the end of the tests whose coverage should be collected. The test
+    /// environments created by `Scenario`, `Bench`, `Differential` and `compare_versions`
+    /// never write coverage.
+    ///
+    /// # Examples
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    /// env.create_user("acc1");
+    ///
+    /// env.write_coverage().unwrap();
+    /// ```
+    pub fn write_coverage(&self) -> io::Result<()> {
+        match env::var_os(COVERAGE_VAR) {
+            Some(dir) if !self.history.is_empty() => self.coverage().write_to_dir(dir),
+            _ => Ok(()),
+        }
+    }
+}
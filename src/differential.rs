@@ -0,0 +1,186 @@
+use std::fmt;
+
+use radix_engine::ledger::SubstateStore;
+use radix_engine::model::Receipt;
+use scrypto::prelude::*;
+
+use crate::fixture::Fixture;
+use crate::{Contents, TestEnv};
+
+type DiffAction<L> = Box<dyn Fn(&mut TestEnv<'_, L>) -> Receipt>;
+
+/// The first step at which two implementations behaved differently.
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    /// The index of the step.
+    pub step: usize,
+    /// The name of the step.
+    pub name: String,
+    /// What differed.
+    pub reason: String,
+    /// The receipt of the reference implementation, rendered.
+    pub reference: String,
+    /// The receipt of the candidate implementation, rendered.
+    pub candidate: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Implementations diverge at step {} ({}): {}",
+            self.step, self.name, self.reason
+        )?;
+        writeln!(f, "Reference:\n{}", self.reference)?;
+        write!(f, "Candidate:\n{}", self.candidate)
+    }
+}
+
+/// Runs the same sequence of calls against two implementations of a blueprint package,
+/// each in its own test environment with identical users, and finds the first step where
+/// success, outputs or user balances differ.
+///
+/// Both packages are published under the same name, so steps can refer to it.
+///
+/// # Examples
+/// ```
+/// use scrypto_unit::*;
+/// use radix_engine::ledger::*;
+/// use scrypto::prelude::*;
+///
+/// let code = include_code!("../tests/assets/hello-world", "hello_world");
+/// let mut differential = Differential::new(
+///     InMemorySubstateStore::with_bootstrap,
+///     "hello_world",
+///     code,
+///     code,
+///     |env| {
+///         env.create_user("alice");
+///     },
+/// );
+/// differential.step("new", |env| env.call_function("Hello", "new", vec!["1".to_owned()]));
+/// differential.step("update", |env| env.call("update_state", vec!["2".to_owned()]));
+///
+/// differential.assert_equivalent();
+/// ```
+pub struct Differential<L: SubstateStore> {
+    fixture: Fixture<L>,
+    name: String,
+    reference: Vec<u8>,
+    candidate: Vec<u8>,
+    steps: Vec<(String, DiffAction<L>)>,
+}
+
+impl<L: SubstateStore> Differential<L> {
+    /// Returns a differential test without steps.
+    ///
+    /// # Arguments
+    ///
+    /// * `ledger` - Creates the ledger of each implementation.
+    /// * `name` - The name both packages are published under.
+    /// * `reference` - The code of the reference implementation.
+    /// * `candidate` - The code of the candidate implementation.
+    /// * `setup` - Prepares both test environments after publishing.
+    pub fn new<M, S>(
+        ledger: M,
+        name: &str,
+        reference: &[u8],
+        candidate: &[u8],
+        setup: S,
+    ) -> Self
+    where
+        M: Fn() -> L + 'static,
+        S: Fn(&mut TestEnv<'_, L>) + 'static,
+    {
+        Self {
+            fixture: Fixture::new(ledger, setup),
+            name: String::from(name),
+            reference: reference.to_vec(),
+            candidate: candidate.to_vec(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Adds a named step, run against both implementations.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the step, used in reports.
+    /// * `action` - The call to make, returning its receipt.
+    pub fn step<F>(&mut self, name: &str, action: F) -> &mut Self
+    where
+        F: Fn(&mut TestEnv<'_, L>) -> Receipt + 'static,
+    {
+        self.steps.push((String::from(name), Box::new(action)));
+
+        self
+    }
+
+    /// Returns the holdings of every user, keyed by user name and resource address.
+    ///
+    /// Both implementations run the same transactions against fresh ledgers, so equivalent
+    /// implementations create the same resource addresses.
+    fn balances(env: &TestEnv<'_, L>) -> HashMap<(String, Address), Contents> {
+        let mut balances = HashMap::new();
+        for (name, user) in &env.users {
+            for (resource, contents) in env.get_holdings(user.account) {
+                balances.insert((name.clone(), resource), contents);
+            }
+        }
+
+        balances
+    }
+
+    /// Runs the steps, returning the first divergence, if any.
+    pub fn run(&self) -> Result<(), Divergence> {
+        let mut reference_ledger = self.fixture.ledger();
+        let mut candidate_ledger = self.fixture.ledger();
+        let mut reference = TestEnv::new(&mut reference_ledger);
+        let mut candidate = TestEnv::new(&mut candidate_ledger);
+
+        reference.publish_package(&self.name, &self.reference);
+        candidate.publish_package(&self.name, &self.candidate);
+        self.fixture.setup(&mut reference);
+        self.fixture.setup(&mut candidate);
+
+        for (i, (name, action)) in self.steps.iter().enumerate() {
+            let reference_receipt = action(&mut reference);
+            let candidate_receipt = action(&mut candidate);
+
+            let reason = if reference_receipt.result.is_ok() != candidate_receipt.result.is_ok()
+            {
+                Some(String::from("success differs"))
+            } else if reference_receipt
+                .outputs
+                .iter()
+                .map(|output| &output.raw)
+                .ne(candidate_receipt.outputs.iter().map(|output| &output.raw))
+            {
+                Some(String::from("outputs differ"))
+            } else if Self::balances(&reference) != Self::balances(&candidate) {
+                Some(String::from("user balances differ"))
+            } else {
+                None
+            };
+
+            if let Some(reason) = reason {
+                return Err(Divergence {
+                    step: i,
+                    name: name.clone(),
+                    reason,
+                    reference: reference.display(&reference_receipt).to_string(),
+                    candidate: candidate.display(&candidate_receipt).to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Panics with the first divergence, if any.
+    pub fn assert_equivalent(&self) {
+        if let Err(divergence) = self.run() {
+            panic!("{}", divergence);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::fmt;
+
+use radix_engine::ledger::SubstateStore;
+use radix_engine::model::{Receipt, ValidatedInstruction};
+use scrypto::prelude::*;
+
+use crate::TestEnv;
+
+/// Renders a `Receipt` with addresses and keys resolved to the names registered in a `TestEnv`.
+///
+/// Created through `TestEnv::display`, mostly to be used in assertion failure messages.
+pub struct ReceiptDisplay<'r, 'a, L: SubstateStore> {
+    env: &'r TestEnv<'a, L>,
+    receipt: &'r Receipt,
+}
+
+impl<'r, 'a, L: SubstateStore> ReceiptDisplay<'r, 'a, L> {
+    pub(crate) fn new(env: &'r TestEnv<'a, L>, receipt: &'r Receipt) -> Self {
+        Self { env, receipt }
+    }
+
+    fn address(&self, address: &Address) -> String {
+        match self.env.lookup_name(address) {
+            Some(name) => name,
+            None => address.to_string(),
+        }
+    }
+
+    fn key(&self, key: &EcdsaPublicKey) -> String {
+        match self.env.lookup_key(key) {
+            Some(name) => name,
+            None => format!("{:?}", key),
+        }
+    }
+
+    pub(crate) fn instruction(&self, instruction: &ValidatedInstruction) -> String {
+        match instruction {
+            ValidatedInstruction::CallFunction {
+                package_address,
+                blueprint_name,
+                function,
+                args,
+            } => format!(
+                "CALL_FUNCTION {}::{}::{}({})",
+                self.address(package_address),
+                blueprint_name,
+                function,
+                args.iter()
+                    .map(|arg| format!("{:?}", arg))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            ValidatedInstruction::CallMethod {
+                component_address,
+                method,
+                args,
+            } => format!(
+                "CALL_METHOD {}.{}({})",
+                self.address(component_address),
+                method,
+                args.iter()
+                    .map(|arg| format!("{:?}", arg))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            ValidatedInstruction::CallMethodWithAllResources {
+                component_address,
+                method,
+            } => format!(
+                "CALL_METHOD_WITH_ALL_RESOURCES {}.{}",
+                self.address(component_address),
+                method
+            ),
+            ValidatedInstruction::TakeFromWorktop {
+                amount,
+                resource_address,
+            } => format!(
+                "TAKE_FROM_WORKTOP {} {}",
+                amount,
+                self.address(resource_address)
+            ),
+            ValidatedInstruction::TakeAllFromWorktop { resource_address } => {
+                format!("TAKE_ALL_FROM_WORKTOP {}", self.address(resource_address))
+            }
+            ValidatedInstruction::PublishPackage { code } => {
+                format!("PUBLISH_PACKAGE ({} bytes)", code.len())
+            }
+            other => format!("{:?}", other),
+        }
+    }
+}
+
+impl<'r, 'a, L: SubstateStore> fmt::Display for ReceiptDisplay<'r, 'a, L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let receipt = self.receipt;
+
+        match &receipt.result {
+            Ok(()) => writeln!(f, "Result: Success")?,
+            Err(error) => writeln!(f, "Result: Failure ({:?})", error)?,
+        }
+
+        let signers: Vec<String> = receipt
+            .transaction
+            .signers
+            .iter()
+            .map(|key| self.key(key))
+            .collect();
+        writeln!(f, "Signers: [{}]", signers.join(", "))?;
+
+        writeln!(f, "Instructions:")?;
+        for (i, instruction) in receipt.transaction.instructions.iter().enumerate() {
+            writeln!(f, "  [{}] {}", i, self.instruction(instruction))?;
+            if let Some(output) = receipt.outputs.get(i) {
+                writeln!(f, "      => {:?}", output)?;
+            }
+        }
+
+        if !receipt.new_entities.is_empty() {
+            writeln!(f, "New Entities:")?;
+            for address in &receipt.new_entities {
+                let kind = if address.is_package() {
+                    "Package"
+                } else if address.is_component() {
+                    "Component"
+                } else {
+                    "ResourceDef"
+                };
+                writeln!(f, "  {}: {}", kind, self.address(address))?;
+            }
+        }
+
+        if !receipt.logs.is_empty() {
+            writeln!(f, "Logs:")?;
+            for (level, message) in &receipt.logs {
+                writeln!(f, "  [{:?}] {}", level, message)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,39 @@
+use radix_engine::ledger::SubstateStore;
+
+use crate::TestEnv;
+
+/// How the harnesses create the fresh test environment of each run: a ledger factory, and
+/// the setup run against the environment.
+pub(crate) struct Fixture<L: SubstateStore> {
+    ledger: Box<dyn Fn() -> L>,
+    setup: Box<dyn Fn(&mut TestEnv<'_, L>)>,
+}
+
+impl<L: SubstateStore> Fixture<L> {
+    /// Returns a fixture.
+    ///
+    /// # Arguments
+    ///
+    /// * `ledger` - Creates the fresh ledger of each run.
+    /// * `setup` - Prepares the test environment of each run.
+    pub(crate) fn new<M, S>(ledger: M, setup: S) -> Self
+    where
+        M: Fn() -> L + 'static,
+        S: Fn(&mut TestEnv<'_, L>) + 'static,
+    {
+        Self {
+            ledger: Box::new(ledger),
+            setup: Box::new(setup),
+        }
+    }
+
+    /// Returns a fresh ledger.
+    pub(crate) fn ledger(&self) -> L {
+        (self.ledger)()
+    }
+
+    /// Runs the setup against a test environment.
+    pub(crate) fn setup(&self, env: &mut TestEnv<'_, L>) {
+        (self.setup)(env)
+    }
+}
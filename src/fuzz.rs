@@ -0,0 +1,372 @@
+use std::fmt;
+
+use radix_engine::ledger::SubstateStore;
+use radix_engine::model::Receipt;
+use radix_engine::transaction::*;
+use sbor::describe::Type;
+use scrypto::prelude::*;
+
+use crate::{Rng, TestEnv, TransactionKind};
+
+/// How a fuzzed call ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FuzzOutcome {
+    /// The call succeeded.
+    Success,
+    /// The blueprint panicked, which is an acceptable way to reject arguments.
+    BlueprintPanic(String),
+    /// The call failed in the engine, e.g. with a wasm trap or a resource check failure.
+    EngineError(String),
+    /// The arguments were rejected while building the transaction.
+    Rejected(String),
+    /// The function or method was not called, as no values can be generated for one of its
+    /// argument types.
+    Skipped(String),
+}
+
+/// A fuzzed call of a blueprint function or component method.
+#[derive(Debug, Clone)]
+pub struct FuzzCase {
+    /// The function or method, as `Blueprint::function` or `<component>.method`.
+    pub target: String,
+    /// The arguments, as passed to the transaction builder.
+    pub args: Vec<String>,
+    /// How the call ended.
+    pub outcome: FuzzOutcome,
+}
+
+/// The calls made by `TestEnv::fuzz_package`, in order.
+#[derive(Debug, Clone)]
+pub struct FuzzReport {
+    /// The seed the arguments were generated from.
+    pub seed: u64,
+    /// The calls made.
+    pub cases: Vec<FuzzCase>,
+}
+
+impl FuzzReport {
+    /// Returns the calls that failed with something other than a blueprint panic.
+    pub fn flagged(&self) -> Vec<&FuzzCase> {
+        self.cases
+            .iter()
+            .filter(|case| matches!(case.outcome, FuzzOutcome::EngineError(_)))
+            .collect()
+    }
+
+    /// Returns the functions and methods that were not called.
+    pub fn skipped(&self) -> Vec<&FuzzCase> {
+        self.cases
+            .iter()
+            .filter(|case| matches!(case.outcome, FuzzOutcome::Skipped(_)))
+            .collect()
+    }
+}
+
+impl fmt::Display for FuzzReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flagged = self.flagged();
+        writeln!(
+            f,
+            "Fuzzed {} calls with seed {}, {} flagged",
+            self.cases.len(),
+            self.seed,
+            flagged.len()
+        )?;
+        for case in flagged {
+            writeln!(
+                f,
+                "  {}({}) => {:?}",
+                case.target,
+                case.args.join(", "),
+                case.outcome
+            )?;
+        }
+        let skipped = self.skipped();
+        if !skipped.is_empty() {
+            writeln!(f, "Skipped {} functions and methods", skipped.len())?;
+            for case in skipped {
+                writeln!(f, "  {} => {:?}", case.target, case.outcome)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn signed<T: ToString>(min: T, max: T) -> Vec<String> {
+    vec![min.to_string(), "-1".to_owned(), "0".to_owned(), max.to_string()]
+}
+
+fn unsigned<T: ToString>(max: T) -> Vec<String> {
+    vec!["0".to_owned(), "1".to_owned(), max.to_string()]
+}
+
+/// The most amounts drawn for a bucket argument, see `TestEnv::fuzz_values`.
+const BUCKET_AMOUNTS: u64 = 4;
+
+/// Returns how many buckets an argument of a type holds at most.
+fn bucket_slots(t: &Type) -> u64 {
+    match t {
+        Type::Custom { name, .. } if name.ends_with("::Bucket") => 1,
+        Type::Vec { element } => BUCKET_AMOUNTS * bucket_slots(element),
+        _ => 0,
+    }
+}
+
+/// A function or method taken from a blueprint ABI.
+struct FuzzTarget {
+    label: String,
+    component: Option<Address>,
+    blueprint: String,
+    name: String,
+    inputs: Vec<Type>,
+}
+
+impl<'a, L: SubstateStore> TestEnv<'a, L> {
+    /// Returns edge-case values for an argument type, or `None` if the transaction builder
+    /// cannot parse arguments of that type.
+    ///
+    /// Vectors are empty, hold one element or every edge case of their element type, in the
+    /// comma-separated form of the transaction builder. Buckets hold XRD, at most `budget`.
+    fn fuzz_values(&self, t: &Type, budget: Decimal) -> Option<Vec<String>> {
+        let values: Vec<String> = match t {
+            Type::Bool => vec!["true".to_owned(), "false".to_owned()],
+            Type::I8 => signed(i8::MIN, i8::MAX),
+            Type::I16 => signed(i16::MIN, i16::MAX),
+            Type::I32 => signed(i32::MIN, i32::MAX),
+            Type::I64 => signed(i64::MIN, i64::MAX),
+            Type::I128 => signed(i128::MIN, i128::MAX),
+            Type::U8 => unsigned(u8::MAX),
+            Type::U16 => unsigned(u16::MAX),
+            Type::U32 => unsigned(u32::MAX),
+            Type::U64 => unsigned(u64::MAX),
+            Type::U128 => unsigned(u128::MAX),
+            Type::String => vec![String::new(), "a".to_owned(), "x".repeat(1024)],
+            Type::Custom { name, .. } if name.ends_with("::Decimal") => vec![
+                "-170141183460469231731.687303715884105728".to_owned(),
+                "-1".to_owned(),
+                "0".to_owned(),
+                "0.000000000000000001".to_owned(),
+                "1".to_owned(),
+                "170141183460469231731.687303715884105727".to_owned(),
+            ],
+            Type::Custom { name, .. } if name.ends_with("::Address") => {
+                let mut addresses: Vec<String> = self
+                    .users
+                    .values()
+                    .map(|user| user.account)
+                    .chain(self.components.values().copied())
+                    .chain(self.resources.values().copied())
+                    .chain(self.packages.values().copied())
+                    .chain(vec![RADIX_TOKEN, SYSTEM_COMPONENT])
+                    .map(|address| address.to_string())
+                    .collect();
+                addresses.sort();
+                addresses.dedup();
+                addresses
+            }
+            Type::Custom { name, .. } if name.ends_with("::Bucket") => {
+                let mut amounts: Vec<Decimal> = vec![0.into(), 1.into(), 1000.into(), budget]
+                    .into_iter()
+                    .filter(|amount| *amount <= budget)
+                    .collect();
+                amounts.dedup();
+                amounts
+                    .into_iter()
+                    .map(|amount| format!("{},{}", amount, RADIX_TOKEN))
+                    .collect()
+            }
+            Type::Vec { element } => {
+                let elements = self.fuzz_values(element, budget)?;
+                vec![String::new(), elements[0].clone(), elements.join(",")]
+            }
+            _ => return None,
+        };
+
+        Some(values)
+    }
+
+    fn fuzz_targets(&self, package: Address, blueprints: &[&str]) -> Vec<FuzzTarget> {
+        let mut targets = Vec::new();
+
+        for &blueprint in blueprints {
+            let abi = match self.executor.export_abi(package, blueprint) {
+                Ok(abi) => abi,
+                Err(error) => panic!("Cannot export ABI of {}: {:?}", blueprint, error),
+            };
+            for function in abi.functions {
+                targets.push(FuzzTarget {
+                    label: format!("{}::{}", blueprint, function.name),
+                    component: None,
+                    blueprint: blueprint.to_owned(),
+                    name: function.name,
+                    inputs: function.inputs,
+                });
+            }
+
+            let mut components: Vec<(&String, &Address)> = self
+                .components
+                .iter()
+                .filter(|(_, address)| {
+                    match self.executor.ledger().get_component(**address) {
+                        Some(c) => {
+                            c.package_address() == package && c.blueprint_name() == blueprint
+                        }
+                        None => false,
+                    }
+                })
+                .collect();
+            components.sort_by(|a, b| a.0.cmp(b.0));
+            for (name, component) in components {
+                for method in &abi.methods {
+                    targets.push(FuzzTarget {
+                        label: format!("{}.{}", name, method.name),
+                        component: Some(*component),
+                        blueprint: blueprint.to_owned(),
+                        name: method.name.clone(),
+                        inputs: method.inputs.clone(),
+                    });
+                }
+            }
+        }
+
+        targets
+    }
+
+    fn classify(receipt: &Receipt) -> FuzzOutcome {
+        match &receipt.result {
+            Ok(()) => FuzzOutcome::Success,
+            Err(error) => {
+                match receipt.logs.iter().find(|(_, message)| message.contains("Panicked at")) {
+                    Some((_, message)) => FuzzOutcome::BlueprintPanic(message.clone()),
+                    None => FuzzOutcome::EngineError(format!("{:?}", error)),
+                }
+            }
+        }
+    }
+
+    /// Calls every function of the given blueprints, and every method of their registered
+    /// components, with SBOR-valid edge-case arguments generated from the package ABI.
+    ///
+    /// Entries taking arguments no values are generated for, such as options, tuples and
+    /// structs, are reported as `FuzzOutcome::Skipped`. Calls are made as the current user;
+    /// the same seed and environment produce the same report. Bucket arguments hold XRD of
+    /// the current user, shared out so that every call can pay for its buckets.
+    ///
+    /// The calls run against this test environment: they are recorded in the history and
+    /// change the ledger, but unlike `call_function` and `call_method` the entities they
+    /// create are not registered, so fuzzing again targets the same components. Fuzz a
+    /// dedicated environment to keep the state of a test untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `package` - The name of the package.
+    /// * `blueprints` - The names of the blueprints to fuzz.
+    /// * `iterations` - The number of calls per function or method.
+    /// * `seed` - The seed the arguments are drawn from.
+    ///
+    /// # Examples
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    /// env.create_user("acc1");
+    /// env.publish_package(
+    ///     "package",
+    ///     include_code!("../tests/assets/hello-world", "hello_world")
+    /// );
+    /// env.call_function("Hello", "new", vec!["1".to_owned()]);
+    ///
+    /// let report = env.fuzz_package("package", &["Hello"], 10, 42);
+    /// assert!(report.flagged().is_empty(), "{}", report);
+    /// ```
+    pub fn fuzz_package(
+        &mut self,
+        package: &str,
+        blueprints: &[&str],
+        iterations: usize,
+        seed: u64,
+    ) -> FuzzReport {
+        let package = self.get_package(package);
+        let user = self.get_current_user();
+        let targets = self.fuzz_targets(package, blueprints);
+        let mut rng = Rng::new(seed);
+        let mut cases = Vec::new();
+
+        for target in targets {
+            let slots: u64 = target.inputs.iter().map(bucket_slots).sum();
+            let budget = match slots {
+                0 => Decimal::from(0),
+                slots => {
+                    let balance = self.get_amount_for_rd(user.account, RADIX_TOKEN);
+                    balance / Decimal::from(slots * iterations.max(1) as u64)
+                }
+            };
+            let values: Option<Vec<Vec<String>>> = target
+                .inputs
+                .iter()
+                .map(|t| self.fuzz_values(t, budget))
+                .collect();
+            let values = match values {
+                Some(values) => values,
+                None => {
+                    cases.push(FuzzCase {
+                        target: target.label.clone(),
+                        args: Vec::new(),
+                        outcome: FuzzOutcome::Skipped(format!(
+                            "unsupported argument types {:?}",
+                            target.inputs
+                        )),
+                    });
+                    continue;
+                }
+            };
+
+            for _ in 0..iterations {
+                let args: Vec<String> = values.iter().map(|v| rng.pick(v).clone()).collect();
+
+                let mut builder = TransactionBuilder::new(&self.executor);
+                match target.component {
+                    Some(component) => builder.call_method(
+                        component,
+                        &target.name,
+                        args.clone(),
+                        Some(user.account),
+                    ),
+                    None => builder.call_function(
+                        package,
+                        &target.blueprint,
+                        &target.name,
+                        args.clone(),
+                        Some(user.account),
+                    ),
+                };
+                let outcome = match builder
+                    .call_method_with_all_resources(user.account, "deposit_batch")
+                    .build(vec![user.key])
+                {
+                    Ok(transaction) => {
+                        let kind = match target.component {
+                            Some(_) => TransactionKind::CallMethod,
+                            None => TransactionKind::CallFunction,
+                        };
+                        let receipt = self.run(kind, transaction);
+                        Self::classify(&receipt)
+                    }
+                    Err(error) => FuzzOutcome::Rejected(format!("{:?}", error)),
+                };
+
+                cases.push(FuzzCase {
+                    target: target.label.clone(),
+                    args,
+                    outcome,
+                });
+            }
+        }
+
+        FuzzReport { seed, cases }
+    }
+}
@@ -0,0 +1,227 @@
+use radix_engine::model::{Receipt, Transaction, ValidatedInstruction};
+use sbor::{Decode, Encode, TypeId};
+use scrypto::prelude::*;
+
+use crate::{Budget, ExecutionMetrics, Trace};
+
+/// The test environment helper a transaction was executed through.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, TypeId, Encode, Decode)]
+pub enum TransactionKind {
+    PublishPackage,
+    CreateUser,
+    CreateToken,
+    CallFunction,
+    CallMethod,
+    Transfer,
+    Fund,
+    Manifest,
+    /// Stores the response of a mock component. Excluded from metrics, coverage, invariants
+    /// and conservation checks.
+    MockResponse,
+}
+
+/// A blueprint function or component method invoked by a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Call {
+    Function {
+        package: Address,
+        blueprint: String,
+        function: String,
+    },
+    Method {
+        component: Address,
+        method: String,
+    },
+}
+
+/// A transaction executed through a `TestEnv`.
+#[derive(Debug, Clone)]
+pub struct TransactionRecord {
+    /// The position of the transaction in the history.
+    pub index: usize,
+    /// The helper the transaction was executed through.
+    pub kind: TransactionKind,
+    /// The name of the first signer, if it is known to the test environment.
+    pub user: Option<String>,
+    /// The transaction as submitted to the executor.
+    pub transaction: Transaction,
+    /// The instructions, rendered with registered names.
+    pub instructions: Vec<String>,
+    /// The functions and methods invoked by the instructions.
+    pub calls: Vec<Call>,
+    /// The result, with the error rendered on failure.
+    pub result: Result<(), String>,
+    /// The SBOR-encoded output of each instruction.
+    pub outputs: Vec<Vec<u8>>,
+    /// The packages, components and resources created.
+    pub new_entities: Vec<Address>,
+    /// The messages logged by the blueprints.
+    pub logs: Vec<String>,
+    /// What the transaction cost to execute.
+    pub metrics: ExecutionMetrics,
+    /// The execution trace, if traces are captured.
+    pub trace: Option<Trace>,
+}
+
+impl TransactionRecord {
+    /// Returns whether the transaction succeeded.
+    pub fn is_success(&self) -> bool {
+        self.result.is_ok()
+    }
+
+    /// Returns whether the transaction invoked a method of the given component.
+    pub fn calls_component(&self, component: Address) -> bool {
+        self.calls.iter().any(|call| match call {
+            Call::Method {
+                component: address,
+                ..
+            } => *address == component,
+            _ => false,
+        })
+    }
+
+    /// Checks the metrics of the transaction against a budget, see `ExecutionMetrics::check`.
+    pub fn check(&self, budget: &Budget) -> Result<(), String> {
+        self.metrics.check(budget)
+    }
+
+    /// Returns whether the transaction invoked a function or method with the given name.
+    pub fn calls_named(&self, name: &str) -> bool {
+        self.calls.iter().any(|call| match call {
+            Call::Function { function, .. } => function == name,
+            Call::Method { method, .. } => method == name,
+        })
+    }
+}
+
+/// What the test environment knows of a transaction besides its receipt, recorded along
+/// with it in the history.
+pub(crate) struct Execution {
+    pub kind: TransactionKind,
+    pub user: Option<String>,
+    pub transaction: Transaction,
+    pub instructions: Vec<String>,
+    pub metrics: ExecutionMetrics,
+    pub trace: Option<Trace>,
+}
+
+/// The ordered list of transactions executed through a `TestEnv`.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    records: Vec<TransactionRecord>,
+}
+
+impl History {
+    /// Returns an empty history.
+    pub fn new() -> Self {
+        Self {
+            records: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, execution: Execution, receipt: &Receipt) {
+        let calls = receipt
+            .transaction
+            .instructions
+            .iter()
+            .filter_map(|instruction| match instruction {
+                ValidatedInstruction::CallFunction {
+                    package_address,
+                    blueprint_name,
+                    function,
+                    ..
+                } => Some(Call::Function {
+                    package: *package_address,
+                    blueprint: blueprint_name.clone(),
+                    function: function.clone(),
+                }),
+                ValidatedInstruction::CallMethod {
+                    component_address,
+                    method,
+                    ..
+                }
+                | ValidatedInstruction::CallMethodWithAllResources {
+                    component_address,
+                    method,
+                } => Some(Call::Method {
+                    component: *component_address,
+                    method: method.clone(),
+                }),
+                _ => None,
+            })
+            .collect();
+
+        self.records.push(TransactionRecord {
+            index: self.records.len(),
+            kind: execution.kind,
+            user: execution.user,
+            transaction: execution.transaction,
+            instructions: execution.instructions,
+            calls,
+            result: receipt
+                .result
+                .as_ref()
+                .map(|_| ())
+                .map_err(|error| format!("{:?}", error)),
+            outputs: receipt
+                .outputs
+                .iter()
+                .map(|output| output.raw.clone())
+                .collect(),
+            new_entities: receipt.new_entities.clone(),
+            logs: receipt
+                .logs
+                .iter()
+                .map(|(level, message)| format!("[{:?}] {}", level, message))
+                .collect(),
+            metrics: execution.metrics,
+            trace: execution.trace,
+        });
+    }
+
+    /// Returns the number of recorded transactions.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Returns whether no transaction was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Returns the most recent transaction.
+    pub fn last(&self) -> Option<&TransactionRecord> {
+        self.records.last()
+    }
+
+    /// Returns the recorded transactions, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &TransactionRecord> {
+        self.records.iter()
+    }
+
+    /// Returns the transactions that failed.
+    pub fn failed(&self) -> Vec<&TransactionRecord> {
+        self.records.iter().filter(|r| !r.is_success()).collect()
+    }
+
+    /// Returns the transactions executed through the given helper.
+    pub fn of_kind(&self, kind: TransactionKind) -> Vec<&TransactionRecord> {
+        self.records.iter().filter(|r| r.kind == kind).collect()
+    }
+
+    /// Returns the transactions signed by the given user.
+    pub fn by_user(&self, name: &str) -> Vec<&TransactionRecord> {
+        self.records
+            .iter()
+            .filter(|r| r.user.as_deref() == Some(name))
+            .collect()
+    }
+
+    /// Returns the transactions invoking a method of the given component.
+    pub fn calls_to(&self, component: Address) -> Vec<&TransactionRecord> {
+        self.records
+            .iter()
+            .filter(|r| r.calls_component(component))
+            .collect()
+    }
+}
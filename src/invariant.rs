@@ -0,0 +1,75 @@
+use std::mem;
+
+use radix_engine::ledger::SubstateStore;
+
+use crate::TestEnv;
+
+/// A check evaluated against the test environment, returning a message on violation.
+pub type Invariant<'a, L> = Box<dyn FnMut(&TestEnv<'a, L>) -> Result<(), String> + 'a>;
+
+impl<'a, L: SubstateStore> TestEnv<'a, L> {
+    /// Registers an invariant evaluated after every successful transaction.
+    ///
+    /// A violated invariant panics, naming the invariant and the transaction that broke it.
+    /// Invariants can keep state between evaluations, e.g. to compare with a previous value.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the invariant, used in reports.
+    /// * `invariant` - The check, returning an error message on violation.
+    ///
+    /// # Examples
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    /// env.create_user("acc1");
+    ///
+    /// env.add_invariant("xrd_conserved", |env| {
+    ///     match env.total_held(RADIX_TOKEN) == env.total_supply(RADIX_TOKEN) {
+    ///         true => Ok(()),
+    ///         false => Err("XRD was minted or burnt".to_owned()),
+    ///     }
+    /// });
+    ///
+    /// env.create_token(10000.into());
+    /// ```
+    pub fn add_invariant<F>(&mut self, name: &str, invariant: F) -> &mut Self
+    where
+        F: FnMut(&TestEnv<'a, L>) -> Result<(), String> + 'a,
+    {
+        self.invariants.push((String::from(name), Box::new(invariant)));
+
+        self
+    }
+
+    /// Removes a registered invariant.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the invariant.
+    pub fn remove_invariant(&mut self, name: &str) -> &mut Self {
+        self.invariants.retain(|(n, _)| n != name);
+
+        self
+    }
+
+    /// Evaluates every registered invariant, returning the first violation.
+    pub fn check_invariants(&mut self) -> Result<(), String> {
+        let mut invariants = mem::take(&mut self.invariants);
+
+        let mut result = Ok(());
+        for (name, invariant) in invariants.iter_mut() {
+            if let Err(message) = invariant(self) {
+                result = Err(format!("Invariant {:?} violated: {}", name, message));
+                break;
+            }
+        }
+
+        self.invariants = invariants;
+        result
+    }
+}
@@ -9,12 +9,59 @@
 extern crate radix_engine;
 extern crate scrypto;
 
-use radix_engine::engine::validate_data;
+mod bench;
+mod blueprints;
+mod calls;
+mod coverage;
+mod differential;
+mod display;
+mod fixture;
+mod fuzz;
+mod history;
+mod invariant;
+mod manifest;
+mod metering;
+mod mock;
+mod scenario;
+mod session;
+mod snapshot;
+mod supply;
+mod trace;
+mod versions;
+
+pub use bench::*;
+pub use blueprints::*;
+pub use calls::*;
+pub use coverage::*;
+pub use differential::*;
+pub use display::*;
+pub use fuzz::*;
+pub use history::*;
+pub use invariant::*;
+pub use metering::*;
+pub use mock::*;
+pub use scenario::*;
+pub use session::*;
+pub use snapshot::*;
+pub use trace::*;
+pub use versions::*;
+
+use radix_engine::engine::{validate_data, ValidatedData};
 use radix_engine::ledger::SubstateStore;
-use radix_engine::model::{Receipt, ValidatedInstruction};
+use radix_engine::model::{Receipt, Transaction, ValidatedInstruction};
 use radix_engine::transaction::*;
 use sbor::Decode;
 use scrypto::prelude::*;
+use std::collections::HashSet;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::time::Instant;
+
+/// The name under which the treasury account is derived and displayed.
+const TREASURY_NAME: &str = "treasury";
+
+/// The amount of XRD handed out by each call to the system faucet.
+const FAUCET_AMOUNT: i32 = 1000000;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 /// The user account.
@@ -36,6 +83,30 @@ pub struct TestEnv<'a, L: SubstateStore> {
     pub packages: HashMap<String, Address>,
     /// The current package of the test environment.
     pub current_package: Option<Address>,
+    /// The published versions of each package, oldest first.
+    pub package_versions: HashMap<String, Vec<String>>,
+    /// The named components of the test environment.
+    pub components: HashMap<String, Address>,
+    /// The named resources of the test environment.
+    pub resources: HashMap<String, Address>,
+    /// The current component of the test environment.
+    pub current_component: Option<Address>,
+    /// The components and resources only registered under a generated name.
+    generated_names: HashSet<Address>,
+    /// The account funding users, created on first use.
+    pub treasury: Option<User>,
+    /// The transactions executed through the test environment.
+    pub history: History,
+    /// Whether supply conservation is checked after every transaction.
+    pub conservation_checks: bool,
+    /// The named invariants evaluated after every successful transaction.
+    invariants: Vec<(String, Invariant<'a, L>)>,
+    /// The substate access counters of a metered ledger.
+    counters: Option<Rc<SubstateCounters>>,
+    /// Whether an execution trace is captured for every transaction.
+    pub capture_traces: bool,
+    /// The mock responses computed before every transaction.
+    mock_responses: Vec<DynamicResponse<'a, L>>,
 }
 
 impl<'a, L: SubstateStore> TestEnv<'a, L> {
@@ -46,6 +117,18 @@ impl<'a, L: SubstateStore> TestEnv<'a, L> {
     /// * `current_user` - The current user of the test environment.
     /// * `packages` - The test environment packages.
     /// * `current_package` - The current package of the test environment.
+    /// * `package_versions` - The published versions of each package, oldest first.
+    /// * `components` - The named components of the test environment.
+    /// * `resources` - The named resources of the test environment.
+    /// * `current_component` - The current component of the test environment.
+    /// * `generated_names` - The components and resources only registered under a generated name.
+    /// * `treasury` - The account funding users, created on first use.
+    /// * `history` - The transactions executed through the test environment.
+    /// * `conservation_checks` - Whether supply conservation is checked after every transaction.
+    /// * `invariants` - The named invariants evaluated after every successful transaction.
+    /// * `counters` - The substate access counters of a metered ledger.
+    /// * `capture_traces` - Whether an execution trace is captured for every transaction.
+    /// * `mock_responses` - The mock responses computed before every transaction.
     ///
     /// # Arguments
     ///
@@ -62,7 +145,11 @@ impl<'a, L: SubstateStore> TestEnv<'a, L> {
     /// let mut env = TestEnv::new(&mut ledger);
     /// ```
     pub fn new(ledger: &'a mut L) -> Self {
-        let executor = TransactionExecutor::new(ledger, false);
+        Self::with_engine_trace(ledger, false)
+    }
+
+    fn with_engine_trace(ledger: &'a mut L, trace: bool) -> Self {
+        let executor = TransactionExecutor::new(ledger, trace);
         let users: HashMap<String, User> = HashMap::new();
         let packages: HashMap<String, Address> = HashMap::new();
 
@@ -72,29 +159,43 @@ impl<'a, L: SubstateStore> TestEnv<'a, L> {
             current_user: None,
             packages,
             current_package: None,
+            package_versions: HashMap::new(),
+            components: HashMap::new(),
+            resources: HashMap::new(),
+            current_component: None,
+            generated_names: HashSet::new(),
+            treasury: None,
+            history: History::new(),
+            conservation_checks: false,
+            invariants: Vec::new(),
+            counters: None,
+            capture_traces: false,
+            mock_responses: Vec::new(),
         }
     }
 
-    /// Returns a test environment instance exactly like `new` but with a tracing executor
+    /// Returns a test environment instance exactly like `new` but capturing a state-diff
+    /// summary of every transaction, see `last_trace`.
+    ///
+    /// Nothing is printed, so tests running in parallel do not interleave their output.
     pub fn new_with_tracing(ledger: &'a mut L) -> Self {
-        let executor = TransactionExecutor::new(ledger, true);
-        let users: HashMap<String, User> = HashMap::new();
-        let packages: HashMap<String, Address> = HashMap::new();
+        let mut env = Self::new(ledger);
+        env.capture_traces = true;
 
-        Self {
-            executor,
-            users,
-            current_user: None,
-            packages,
-            current_package: None,
-        }
+        env
+    }
+
+    /// Returns a test environment instance exactly like `new` but with the engine's trace
+    /// output printed to stdout.
+    pub fn new_with_engine_trace(ledger: &'a mut L) -> Self {
+        Self::with_engine_trace(ledger, true)
     }
 
     /// Publishes a given package to the transaction execution ledger.
     ///
     /// # Arguments
     ///
-    /// * `name` - The name of the package.
+    /// * `name` - The name of the package, which must not be registered already.
     /// * `package` - The package as a binary array.
     ///
     /// # Examples
@@ -113,7 +214,22 @@ impl<'a, L: SubstateStore> TestEnv<'a, L> {
     /// );
     /// ```
     pub fn publish_package(&mut self, name: &str, package: &[u8]) -> &mut Self {
-        let package_addr = self.executor.publish_package(package).unwrap();
+        if self.packages.contains_key(name) {
+            panic!(
+                "A package named {:?} is already published, use publish_package_version to publish several versions",
+                name
+            );
+        }
+
+        let transaction = TransactionBuilder::new(&self.executor)
+            .publish_package(package)
+            .build(Vec::new())
+            .unwrap();
+        let receipt = self.run(TransactionKind::PublishPackage, transaction);
+        let package_addr = match receipt.package(0) {
+            Some(package_addr) => package_addr,
+            None => panic!("Failed to publish package {:?}:\n{}", name, self.display(&receipt)),
+        };
         self.packages.insert(String::from(name), package_addr);
 
         //If first package set as default
@@ -125,6 +241,77 @@ impl<'a, L: SubstateStore> TestEnv<'a, L> {
         self
     }
 
+    /// Publishes a version of a package, registered as `name@version`.
+    ///
+    /// The plain `name` refers to the latest published version, and so does the current
+    /// package when it was the previous version.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The logical name of the package, which must not be registered already by
+    ///   `publish_package`.
+    /// * `version` - The version of the package.
+    /// * `package` - The package as a binary array.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    ///
+    /// let code = include_code!("../tests/assets/hello-world", "hello_world");
+    /// env.publish_package_version("hello_world", "v1", code);
+    /// env.publish_package_version("hello_world", "v2", code);
+    ///
+    /// assert_eq!(env.get_package("hello_world"), env.get_package("hello_world@v2"));
+    /// assert_eq!(env.get_current_package(), env.get_package("hello_world@v2"));
+    /// assert_eq!(env.get_package_versions("hello_world"), vec!["v1", "v2"]);
+    /// ```
+    pub fn publish_package_version(
+        &mut self,
+        name: &str,
+        version: &str,
+        package: &[u8],
+    ) -> &mut Self {
+        if self.packages.contains_key(name) && !self.package_versions.contains_key(name) {
+            panic!(
+                "A package named {:?} is already published without a version, use publish_package_version for every version",
+                name
+            );
+        }
+
+        let versioned = format!("{}@{}", name, version);
+        self.publish_package(&versioned, package);
+
+        let package_addr = self.get_package(&versioned);
+        let previous = self.packages.insert(String::from(name), package_addr);
+        if previous.is_some() && self.current_package == previous {
+            self.current_package = Some(package_addr);
+        }
+        self.package_versions
+            .entry(String::from(name))
+            .or_insert_with(Vec::new)
+            .push(String::from(version));
+
+        self
+    }
+
+    /// Returns the published versions of a package, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The logical name of the package.
+    pub fn get_package_versions(&self, name: &str) -> Vec<String> {
+        match self.package_versions.get(name) {
+            Some(versions) => versions.clone(),
+            None => Vec::new(),
+        }
+    }
+
     /// Retrieve a test environment package by name.
     ///
     /// # Arguments
@@ -187,6 +374,13 @@ impl<'a, L: SubstateStore> TestEnv<'a, L> {
 
     /// Create a test user.
     ///
+    /// The user's key is derived from its name, so the same named user always gets the
+    /// same key regardless of the order in which users are created.
+    ///
+    /// The account address is not: the ledger allocates it from its transaction nonce, so
+    /// creating another user or running any transaction first still shifts it. Refer to
+    /// accounts by user name, as `lookup_name` and `snapshot` do, to keep output stable.
+    ///
     /// # Arguments
     ///
     /// * `name` - The name of the user.
@@ -204,8 +398,37 @@ impl<'a, L: SubstateStore> TestEnv<'a, L> {
     /// env.create_user("test user");
     /// ```
     pub fn create_user(&mut self, name: &str) -> User {
-        let key = self.executor.new_public_key();
-        let account = self.executor.new_account(key);
+        self.create_user_with_seed(name, 0)
+    }
+
+    /// Create a test user whose key is derived from its name and a seed.
+    ///
+    /// Only the key is deterministic. The account address is allocated by the ledger from its
+    /// transaction nonce, which `SubstateStore` offers no way to set, so it is stable across
+    /// runs of the same sequence of transactions but shifts with anything that ran before.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the user.
+    /// * `seed` - The seed mixed into the key derivation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    ///
+    /// let user = env.create_user_with_seed("test user", 42);
+    ///
+    /// assert_eq!(user.key, TestEnv::<InMemorySubstateStore>::derive_key("test user", 42));
+    /// ```
+    pub fn create_user_with_seed(&mut self, name: &str, seed: u64) -> User {
+        let key = Self::derive_key(name, seed);
+        let account = self.new_account(key);
 
         self.users.insert(String::from(name), User { key, account });
 
@@ -220,6 +443,21 @@ impl<'a, L: SubstateStore> TestEnv<'a, L> {
         usr
     }
 
+    /// Derives a public key from a user name and a seed.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the user.
+    /// * `seed` - The seed mixed into the key derivation.
+    pub fn derive_key(name: &str, seed: u64) -> EcdsaPublicKey {
+        let hash = sha256(format!("scrypto-unit:{}:{}", name, seed));
+        let mut raw = [0u8; 33];
+        raw[0] = 0x02;
+        raw[1..].copy_from_slice(&hash.0);
+
+        EcdsaPublicKey(raw)
+    }
+
     /// Retrieve a test user by name.
     ///
     /// # Arguments
@@ -276,6 +514,71 @@ impl<'a, L: SubstateStore> TestEnv<'a, L> {
         self
     }
 
+    /// Runs a closure with the given user as the current user, then restores the previous
+    /// current user, even if the closure panics.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the user.
+    /// * `f` - The closure to run.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    ///
+    /// let alice = env.create_user("alice");
+    /// let admin = env.create_user("admin");
+    ///
+    /// let current = env.as_user("admin", |env| env.get_current_user());
+    ///
+    /// assert_eq!(current, admin);
+    /// assert_eq!(env.get_current_user(), alice);
+    /// ```
+    pub fn as_user<F, R>(&mut self, name: &str, f: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let previous = self.current_user;
+        self.acting_as(name);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(self)));
+        self.current_user = previous;
+
+        match result {
+            Ok(value) => value,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+
+    /// Runs a closure with the given package as the current package, then restores the
+    /// previous current package, even if the closure panics.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the package.
+    /// * `f` - The closure to run.
+    pub fn with_package<F, R>(&mut self, name: &str, f: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let previous = self.current_package;
+        self.using_package(name);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(self)));
+        self.current_package = previous;
+
+        match result {
+            Ok(value) => value,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+
     /// Returns the current test user.
     ///
     /// # Examples
@@ -327,6 +630,60 @@ impl<'a, L: SubstateStore> TestEnv<'a, L> {
         }
     }
 
+    /// Returns the current epoch of the ledger.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    ///
+    /// env.set_epoch(10);
+    /// assert_eq!(env.current_epoch(), 10);
+    /// ```
+    pub fn current_epoch(&self) -> u64 {
+        self.executor.ledger().get_epoch()
+    }
+
+    /// Sets the current epoch of the ledger, as seen by `Context::current_epoch()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `epoch` - The new epoch.
+    pub fn set_epoch(&mut self, epoch: u64) -> &mut Self {
+        self.executor.ledger_mut().set_epoch(epoch);
+
+        self
+    }
+
+    /// Moves the current epoch of the ledger forward.
+    ///
+    /// # Arguments
+    ///
+    /// * `epochs` - The number of epochs to advance by.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    ///
+    /// env.set_epoch(10).advance_epochs(5);
+    /// assert_eq!(env.current_epoch(), 15);
+    /// ```
+    pub fn advance_epochs(&mut self, epochs: u64) -> &mut Self {
+        let epoch = self.current_epoch();
+        self.set_epoch(epoch + epochs)
+    }
+
     /// Creates a token returns a ResourceDef
     /// # Arguments
     ///
@@ -344,16 +701,13 @@ impl<'a, L: SubstateStore> TestEnv<'a, L> {
     /// ```
     pub fn create_token(&mut self, max_supply: Decimal) -> ResourceDef {
         let user = self.get_current_user();
-        let receipt = self
-            .executor
-            .run(
-                TransactionBuilder::new(&self.executor)
-                    .new_token_fixed(HashMap::new(), max_supply.into())
-                    .call_method_with_all_resources(user.account, "deposit_batch")
-                    .build(vec![user.key])
-                    .unwrap(),
-            )
+        let transaction = TransactionBuilder::new(&self.executor)
+            .new_token_fixed(HashMap::new(), max_supply.into())
+            .call_method_with_all_resources(user.account, "deposit_batch")
+            .build(vec![user.key])
             .unwrap();
+        let receipt = self.run(TransactionKind::CreateToken, transaction);
+        self.register_new_entities(&receipt);
 
         return receipt.resource_def(0).unwrap().into();
     }
@@ -389,27 +743,27 @@ impl<'a, L: SubstateStore> TestEnv<'a, L> {
     ) -> Receipt {
         let user = self.get_current_user();
         let package = self.get_current_package();
-        self.executor
-            .run(
-                TransactionBuilder::new(&self.executor)
-                    .call_function(
-                        package,
-                        blueprint_name,
-                        function_name,
-                        params,
-                        Some(user.account),
-                    )
-                    .call_method_with_all_resources(user.account, "deposit_batch")
-                    .build(vec![user.key])
-                    .unwrap(),
+        let transaction = TransactionBuilder::new(&self.executor)
+            .call_function(
+                package,
+                blueprint_name,
+                function_name,
+                params,
+                Some(user.account),
             )
-            .unwrap()
+            .call_method_with_all_resources(user.account, "deposit_batch")
+            .build(vec![user.key])
+            .unwrap();
+        let receipt = self.run(TransactionKind::CallFunction, transaction);
+        self.register_new_entities(&receipt);
+
+        receipt
     }
 
     /// Makes a method call and returns a Receipt
     /// # Arguments
     ///
-    /// * `Component`   - A reference to the Address of the component
+    /// * `component`   - The Address of the component or its registered name
     /// * `method_name` - The name of the method
     /// * `params`      - A vector of Strings with the arguments to pass in the method
     ///
@@ -441,21 +795,360 @@ impl<'a, L: SubstateStore> TestEnv<'a, L> {
     /// ```
     pub fn call_method(
         &mut self,
-        component: &Address,
+        component: impl ToAddress,
         method_name: &str,
         params: Vec<String>,
     ) -> Receipt {
         let user = self.get_current_user();
+        let component = component.to_address(self);
 
-        self.executor
-            .run(
-                TransactionBuilder::new(&self.executor)
-                    .call_method(*component, method_name, params, Some(user.account))
-                    .call_method_with_all_resources(user.account, "deposit_batch")
-                    .build(vec![user.key])
-                    .unwrap(),
-            )
-            .unwrap()
+        let transaction = TransactionBuilder::new(&self.executor)
+            .call_method(component, method_name, params, Some(user.account))
+            .call_method_with_all_resources(user.account, "deposit_batch")
+            .build(vec![user.key])
+            .unwrap();
+        let receipt = self.run(TransactionKind::CallMethod, transaction);
+        self.register_new_entities(&receipt);
+
+        receipt
+    }
+
+    /// Registers a component under a name, so it can be used in place of its address.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the component.
+    /// * `address` - The address of the component.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    /// env.create_user("acc1");
+    /// env.publish_package(
+    ///     "package",
+    ///     include_code!("../tests/assets/hello-world", "hello_world")
+    /// );
+    ///
+    /// let receipt = env.call_function("Hello", "new", vec!["1".to_owned()]);
+    /// env.register_component("hello", receipt.component(0).unwrap());
+    ///
+    /// let receipt = env.call_method("hello", "update_state", vec!["2".to_owned()]);
+    /// assert!(receipt.result.is_ok());
+    /// ```
+    pub fn register_component(&mut self, name: &str, address: Address) -> &mut Self {
+        if self.generated_names.remove(&address) {
+            self.components.retain(|_, component| *component != address);
+        }
+        self.insert_component(name, address);
+
+        self
+    }
+
+    fn insert_component(&mut self, name: &str, address: Address) {
+        self.components.insert(String::from(name), address);
+
+        //If first component set as default
+        match self.current_component {
+            Some(_) => {}
+            None => self.current_component = Some(address),
+        }
+    }
+
+    /// Retrieve a test environment component by name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the component.
+    pub fn get_component(&self, name: &str) -> Address {
+        match self.components.get(name) {
+            Some(&component) => component,
+            None => panic!("No component named {:?} found.", name),
+        }
+    }
+
+    /// Sets the current component of the test environment.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the component.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    /// env.create_user("acc1");
+    /// env.publish_package(
+    ///     "package",
+    ///     include_code!("../tests/assets/hello-world", "hello_world")
+    /// );
+    ///
+    /// let receipt = env.call_function("Hello", "new", vec!["1".to_owned()]);
+    /// env.register_component("hello", receipt.component(0).unwrap());
+    ///
+    /// env.using_component("hello");
+    /// ```
+    pub fn using_component(&mut self, name: &str) -> &mut Self {
+        let component = self.get_component(name);
+        self.current_component = Some(component);
+
+        self
+    }
+
+    /// Returns the current test component.
+    pub fn get_current_component(&self) -> Address {
+        match self.current_component {
+            Some(component) => component,
+            None => panic!("Fatal error, no component specified aborting"),
+        }
+    }
+
+    /// Makes a method call on the current component and returns a Receipt
+    /// # Arguments
+    ///
+    /// * `method_name` - The name of the method
+    /// * `params`      - A vector of Strings with the arguments to pass in the method
+    ///
+    /// # Examples
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    ///
+    /// env.create_user("acc1");
+    /// env.publish_package(
+    ///     "package",
+    ///     include_code!("../tests/assets/hello-world", "hello_world")
+    /// );
+    ///
+    /// let receipt = env.call_function("Hello", "new", vec!["1".to_owned()]);
+    /// assert!(receipt.result.is_ok());
+    ///
+    /// let receipt = env.call("update_state", vec!["2".to_owned()]);
+    /// assert!(receipt.result.is_ok());
+    /// ```
+    pub fn call(&mut self, method_name: &str, params: Vec<String>) -> Receipt {
+        let component = self.get_current_component();
+
+        self.call_method(component, method_name, params)
+    }
+
+    /// Registers a resource under a name, so it can be used in place of its address.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the resource.
+    /// * `address` - The address of the resource definition.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    ///
+    /// let user = env.create_user("acc1");
+    /// env.register_resource("XRD", RADIX_TOKEN);
+    ///
+    /// let amount = env.get_amount_for_rd(user.account, "XRD");
+    /// assert!(amount == 1000000.into());
+    /// ```
+    pub fn register_resource(&mut self, name: &str, address: Address) -> &mut Self {
+        if self.generated_names.remove(&address) {
+            self.resources.retain(|_, resource| *resource != address);
+        }
+        self.resources.insert(String::from(name), address);
+
+        self
+    }
+
+    /// Retrieve a test environment resource by name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the resource.
+    pub fn get_resource(&self, name: &str) -> Address {
+        match self.resources.get(name) {
+            Some(&resource) => resource,
+            None => panic!("No resource named {:?} found.", name),
+        }
+    }
+
+    /// Resolves a name registered in the test environment to its address.
+    ///
+    /// Components are searched first, then user accounts, resources and packages.
+    pub fn resolve(&self, name: &str) -> Address {
+        if let Some(&address) = self.components.get(name) {
+            return address;
+        }
+        if let Some(user) = self.users.get(name) {
+            return user.account;
+        }
+        if let Some(&address) = self.resources.get(name) {
+            return address;
+        }
+        if let Some(&address) = self.packages.get(name) {
+            return address;
+        }
+
+        panic!("No component, user, resource or package named {:?} found.", name)
+    }
+
+    /// Registers the components and resources created by a transaction.
+    ///
+    /// Components are named after their blueprint and resources after their `symbol`
+    /// metadata, with a numeric suffix when the name is already taken.
+    pub(crate) fn register_new_entities(&mut self, receipt: &Receipt) {
+        for address in &receipt.new_entities {
+            if address.is_component() {
+                let blueprint = match self.executor.ledger().get_component(*address) {
+                    Some(component) => component.blueprint_name().to_owned(),
+                    None => continue,
+                };
+                let name = Self::unique_name(&self.components, &blueprint);
+                self.insert_component(&name, *address);
+                self.generated_names.insert(*address);
+            } else if address.is_resource_def() {
+                let symbol = match self.executor.ledger().get_resource_def(*address) {
+                    Some(resource_def) => resource_def
+                        .metadata()
+                        .get("symbol")
+                        .cloned()
+                        .unwrap_or_else(|| String::from("resource")),
+                    None => continue,
+                };
+                let name = Self::unique_name(&self.resources, &symbol);
+                self.resources.insert(name, *address);
+                self.generated_names.insert(*address);
+            }
+        }
+    }
+
+    fn unique_name(names: &HashMap<String, Address>, base: &str) -> String {
+        if !names.contains_key(base) {
+            return String::from(base);
+        }
+
+        let mut n = 2;
+        loop {
+            let name = format!("{}_{}", base, n);
+            if !names.contains_key(&name) {
+                return name;
+            }
+            n += 1;
+        }
+    }
+
+    /// Returns the name registered in the test environment for an address, if any.
+    ///
+    /// User accounts resolve to the user name, and components, resources and packages
+    /// to the name they were registered under. An explicit registration replaces the name
+    /// generated for a new entity, and other aliases resolve to the shortest, then
+    /// alphabetically first, name.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to look up.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    ///
+    /// let user = env.create_user("alice");
+    ///
+    /// assert_eq!(env.lookup_name(&user.account), Some("alice".to_owned()));
+    /// ```
+    pub fn lookup_name(&self, address: &Address) -> Option<String> {
+        if let Some((name, _)) = self.users.iter().find(|(_, user)| user.account == *address) {
+            return Some(name.clone());
+        }
+        if let Some(treasury) = self.treasury {
+            if treasury.account == *address {
+                return Some(String::from(TREASURY_NAME));
+            }
+        }
+        Self::stable_name(&self.components, address)
+            .or_else(|| Self::stable_name(&self.resources, address))
+            .or_else(|| Self::stable_name(&self.packages, address))
+    }
+
+    /// Returns the shortest, then alphabetically first, of the names an address is registered
+    /// under, so that aliases resolve the same way in every run.
+    pub(crate) fn stable_name(
+        names: &HashMap<String, Address>,
+        address: &Address,
+    ) -> Option<String> {
+        names
+            .iter()
+            .filter(|(_, a)| *a == address)
+            .map(|(name, _)| name)
+            .min_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b)))
+            .cloned()
+    }
+
+    /// Returns the name of the user, or the treasury, owning a key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The public key to look up.
+    pub fn lookup_key(&self, key: &EcdsaPublicKey) -> Option<String> {
+        if let Some((name, _)) = self.users.iter().find(|(_, user)| user.key == *key) {
+            return Some(name.clone());
+        }
+        match self.treasury {
+            Some(treasury) if treasury.key == *key => Some(String::from(TREASURY_NAME)),
+            _ => None,
+        }
+    }
+
+    /// Returns a `Display` renderer of a receipt that resolves the names of users and packages.
+    ///
+    /// # Arguments
+    ///
+    /// * `receipt` - The receipt to render.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    /// env.create_user("alice");
+    /// env.publish_package(
+    ///     "hello_world",
+    ///     include_code!("../tests/assets/hello-world", "hello_world")
+    /// );
+    ///
+    /// let receipt = env.call_function("Hello", "new", vec!["1".to_owned()]);
+    /// assert!(receipt.result.is_ok(), "{}", env.display(&receipt));
+    /// ```
+    pub fn display<'r>(&'r self, receipt: &'r Receipt) -> ReceiptDisplay<'r, 'a, L> {
+        ReceiptDisplay::new(self, receipt)
     }
 
     fn get_vault_info(ledger: &L, component_address: &Address, vid: &Vid) -> (Address, Contents) {
@@ -496,8 +1189,8 @@ impl<'a, L: SubstateStore> TestEnv<'a, L> {
     /// Returns the amount of the resource for the component/account
     /// # Arguments
     ///
-    /// * `component_address`    - The Address of the component that holds the resource
-    /// * `resource_def` - The Address that holds the resource
+    /// * `component_address`    - The Address or registered name of the component that holds the resource
+    /// * `resource_def` - The Address or registered name of the resource
     ///
     /// # Examples
     /// ```
@@ -514,9 +1207,10 @@ impl<'a, L: SubstateStore> TestEnv<'a, L> {
     /// ```
     pub fn get_amount_for_rd(
         &mut self,
-        component_address: Address,
-        resource_def: Address,
+        component_address: impl ToAddress,
+        resource_def: impl ToAddress,
     ) -> Decimal {
+        let resource_def = resource_def.to_address(self);
         let vaults = self.get_account_vaults(component_address);
         for (address, contents) in vaults {
             if address == resource_def {
@@ -531,7 +1225,8 @@ impl<'a, L: SubstateStore> TestEnv<'a, L> {
     }
 
 
-    pub fn get_non_fungible_keys_for_rd(&mut self, component_address: Address, resource_def: Address) -> Vec<NonFungibleKey> {
+    pub fn get_non_fungible_keys_for_rd(&mut self, component_address: impl ToAddress, resource_def: impl ToAddress) -> Vec<NonFungibleKey> {
+        let resource_def = resource_def.to_address(self);
         let vaults = self.get_account_vaults(component_address);
         for (address, contents) in vaults {
             if address == resource_def {
@@ -545,7 +1240,8 @@ impl<'a, L: SubstateStore> TestEnv<'a, L> {
         Vec::new()
     }
     
-    pub fn get_account_vaults(&mut self, component_address: Address) -> HashMap<Address, Contents> {
+    pub fn get_account_vaults(&mut self, component_address: impl ToAddress) -> HashMap<Address, Contents> {
+        let component_address = component_address.to_address(self);
         let ledger = self.executor.ledger();
         let component = ledger.get_component(component_address).unwrap();
         let state = component.state();
@@ -557,11 +1253,73 @@ impl<'a, L: SubstateStore> TestEnv<'a, L> {
             .collect()
     }
 
+    fn collect_vaults(
+        ledger: &L,
+        component_address: &Address,
+        data: &ValidatedData,
+        holdings: &mut HashMap<Address, Contents>,
+    ) {
+        for vid in &data.vaults {
+            let (resource, contents) = TestEnv::get_vault_info(ledger, component_address, vid);
+            let merged = match (holdings.remove(&resource), contents) {
+                (Some(Contents::Amount(a)), Contents::Amount(b)) => Contents::Amount(a + b),
+                (Some(Contents::NonFungibleKeys(mut a)), Contents::NonFungibleKeys(b)) => {
+                    a.extend(b);
+                    Contents::NonFungibleKeys(a)
+                }
+                (_, contents) => contents,
+            };
+            holdings.insert(resource, merged);
+        }
+        for mid in &data.lazy_maps {
+            if let Some(lazy_map) = ledger.get_lazy_map(component_address, mid) {
+                for (_, value) in lazy_map.map().iter() {
+                    let value = validate_data(value).unwrap();
+                    TestEnv::collect_vaults(ledger, component_address, &value, holdings);
+                }
+            }
+        }
+    }
+
+    /// Returns everything held by a component or account, per resource.
+    ///
+    /// Unlike `get_account_vaults`, vaults held directly in the component state and vaults
+    /// in nested lazy maps are included, and vaults of the same resource are merged.
+    ///
+    /// # Arguments
+    ///
+    /// * `component_address` - The Address or registered name of the component
+    ///
+    /// # Examples
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    ///
+    /// env.create_user("acc1");
+    /// let holdings = env.get_holdings("acc1");
+    /// assert_eq!(holdings.get(&RADIX_TOKEN), Some(&Contents::Amount(1000000.into())));
+    /// ```
+    pub fn get_holdings(&self, component_address: impl ToAddress) -> HashMap<Address, Contents> {
+        let component_address = component_address.to_address(self);
+        let ledger = self.executor.ledger();
+        let component = ledger.get_component(component_address).unwrap();
+        let state = validate_data(component.state()).unwrap();
+
+        let mut holdings = HashMap::new();
+        TestEnv::collect_vaults(ledger, &component_address, &state, &mut holdings);
+
+        holdings
+    }
+
     /// Transfers some resource between users
     /// # Arguments
     ///
     /// * `amount` - A decimal that defines the amount to transfer
-    /// * `resource_def` - The resource_def for the resource to transfer
+    /// * `resource_def` - The resource_def, address or registered name of the resource to transfer
     /// * `to_user` - the user receiving the amount of resource
     ///
     /// # Examples
@@ -579,31 +1337,322 @@ impl<'a, L: SubstateStore> TestEnv<'a, L> {
     pub fn transfer_resource(
         &mut self,
         amount: Decimal,
-        resource_def: &ResourceDef,
+        resource_def: impl ToAddress,
         to_user: &User,
     ) -> Receipt {
         let user = self.get_current_user();
-        let receipt = self
-            .executor
-            .run(
-                TransactionBuilder::new(&self.executor)
-                    .withdraw_from_account(
-                        &Resource::Fungible {
-                            amount,
-                            resource_address: resource_def.address(),
-                        },
-                        user.account,
-                    )
-                    .call_method_with_all_resources(to_user.account, "deposit_batch")
-                    .build(vec![user.key])
-                    .unwrap(),
+        let resource_address = resource_def.to_address(self);
+        let transaction = TransactionBuilder::new(&self.executor)
+            .withdraw_from_account(
+                &Resource::Fungible {
+                    amount,
+                    resource_address,
+                },
+                user.account,
             )
+            .call_method_with_all_resources(to_user.account, "deposit_batch")
+            .build(vec![user.key])
             .unwrap();
+        let receipt = self.run(TransactionKind::Transfer, transaction);
 
         receipt
     }
+
+    /// Returns the treasury account used to fund users, creating it on first use.
+    ///
+    /// XRD is drawn from the system faucet on demand. Any other resource must first be
+    /// deposited into the treasury, e.g. with `transfer_resource`.
+    ///
+    /// # Examples
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::InMemorySubstateStore;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    /// env.create_user("user1");
+    /// let token = env.create_token(10000.into());
+    /// let treasury = env.treasury();
+    /// env.transfer_resource(5000.into(), &token, &treasury);
+    /// ```
+    pub fn treasury(&mut self) -> User {
+        match self.treasury {
+            Some(treasury) => treasury,
+            None => {
+                let key = Self::derive_key(TREASURY_NAME, 0);
+                let account = self.new_account(key);
+                let treasury = User { key, account };
+                self.treasury = Some(treasury);
+
+                treasury
+            }
+        }
+    }
+
+    /// Create a test user holding the given amount of XRD.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the user.
+    /// * `xrd_amount` - The amount of XRD the user's account starts with.
+    ///
+    /// # Examples
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    ///
+    /// let user = env.create_user_with_balance("whale", 5000000.into());
+    /// assert!(env.get_amount_for_rd(user.account, RADIX_TOKEN) == 5000000.into());
+    ///
+    /// let user = env.create_user_with_balance("minnow", 10.into());
+    /// assert!(env.get_amount_for_rd(user.account, RADIX_TOKEN) == 10.into());
+    /// ```
+    pub fn create_user_with_balance(&mut self, name: &str, xrd_amount: Decimal) -> User {
+        let user = self.create_user(name);
+        let balance = self.get_amount_for_rd(user.account, RADIX_TOKEN);
+
+        if balance > xrd_amount {
+            let treasury = self.treasury();
+            let receipt = self.move_resource(user, treasury.account, RADIX_TOKEN, balance - xrd_amount);
+            assert!(receipt.result.is_ok(), "{}", self.display(&receipt));
+        } else if balance < xrd_amount {
+            let receipt = self.fund(name, RADIX_TOKEN, xrd_amount - balance);
+            assert!(receipt.result.is_ok(), "{}", self.display(&receipt));
+        }
+
+        user
+    }
+
+    /// Tops up a user with some resource from the treasury.
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - The name of the user to fund.
+    /// * `resource_def` - The address or registered name of the resource.
+    /// * `amount` - The amount to fund the user with.
+    ///
+    /// # Examples
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    ///
+    /// let user = env.create_user("acc1");
+    /// let receipt = env.fund("acc1", RADIX_TOKEN, 500.into());
+    /// assert!(receipt.result.is_ok());
+    /// assert!(env.get_amount_for_rd(user.account, RADIX_TOKEN) == 1000500.into());
+    /// ```
+    pub fn fund(&mut self, user: &str, resource_def: impl ToAddress, amount: Decimal) -> Receipt {
+        let to = self.get_user(user).account;
+        let resource_address = resource_def.to_address(self);
+        let treasury = self.treasury();
+
+        if resource_address == RADIX_TOKEN {
+            let mut drawn = Decimal::from(0);
+            let mut builder = TransactionBuilder::new(&self.executor);
+            while drawn < amount {
+                builder.call_method(SYSTEM_COMPONENT, "free_xrd", vec![], None);
+                drawn = drawn + Decimal::from(FAUCET_AMOUNT);
+            }
+            let transaction = builder
+                .call_method_with_all_resources(treasury.account, "deposit_batch")
+                .build(vec![treasury.key])
+                .unwrap();
+            let receipt = self.run(TransactionKind::Fund, transaction);
+            if receipt.result.is_err() {
+                return receipt;
+            }
+        }
+
+        self.move_resource(treasury, to, resource_address, amount)
+    }
+
+    fn new_account(&mut self, key: EcdsaPublicKey) -> Address {
+        let transaction = TransactionBuilder::new(&self.executor)
+            .call_method(SYSTEM_COMPONENT, "free_xrd", vec![], None)
+            .new_account_with_resource(
+                key,
+                &Resource::Fungible {
+                    amount: FAUCET_AMOUNT.into(),
+                    resource_address: RADIX_TOKEN,
+                },
+            )
+            .build(Vec::new())
+            .unwrap();
+        let receipt = self.run(TransactionKind::CreateUser, transaction);
+
+        match receipt.component(0) {
+            Some(account) => account,
+            None => panic!("Failed to create account:\n{}", self.display(&receipt)),
+        }
+    }
+
+    /// Runs a transaction and appends it to the history of the test environment.
+    pub(crate) fn run(&mut self, kind: TransactionKind, transaction: Transaction) -> Receipt {
+        if !self.mock_responses.is_empty() {
+            self.refresh_mock_responses();
+        }
+        let recorded = transaction.clone();
+        let state_before = match self.capture_traces {
+            true => Some(self.capture_state()),
+            false => None,
+        };
+        let before = self.counters.as_ref().map(|counters| counters.get());
+        let start = Instant::now();
+        let receipt = self.executor.run(transaction).unwrap();
+        let elapsed = start.elapsed();
+        let accesses = match (&self.counters, before) {
+            (Some(counters), Some((reads, writes, bytes))) => {
+                let (r, w, b) = counters.get();
+                Some((r - reads, w - writes, b - bytes))
+            }
+            _ => None,
+        };
+        let metrics = ExecutionMetrics {
+            transactions: 1,
+            manifest_instructions: receipt.transaction.instructions.len(),
+            elapsed,
+            substate_reads: accesses.map(|(reads, _, _)| reads),
+            substate_writes: accesses.map(|(_, writes, _)| writes),
+            bytes_written: accesses.map(|(_, _, bytes)| bytes),
+        };
+        let user = receipt
+            .transaction
+            .signers
+            .first()
+            .and_then(|key| self.lookup_key(key));
+        let instructions = {
+            let display = self.display(&receipt);
+            receipt
+                .transaction
+                .instructions
+                .iter()
+                .map(|instruction| display.instruction(instruction))
+                .collect()
+        };
+        let trace = state_before.map(|state_before| self.build_trace(&receipt, &state_before));
+        let execution = Execution {
+            kind,
+            user,
+            transaction: recorded,
+            instructions,
+            metrics,
+            trace,
+        };
+        self.history.push(execution, &receipt);
+
+        let checked = kind != TransactionKind::MockResponse;
+        if checked && self.conservation_checks {
+            if let Err(violation) = self.check_supply_conservation() {
+                panic!("{}\n{}", violation, self.describe_last(&receipt));
+            }
+        }
+        if checked && receipt.result.is_ok() && !self.invariants.is_empty() {
+            if let Err(violation) = self.check_invariants() {
+                panic!(
+                    "{} after transaction {}\n{}",
+                    violation,
+                    self.history.len() - 1,
+                    self.describe_last(&receipt)
+                );
+            }
+        }
+
+        receipt
+    }
+
+    fn move_resource(
+        &mut self,
+        from: User,
+        to: Address,
+        resource_address: Address,
+        amount: Decimal,
+    ) -> Receipt {
+        let transaction = TransactionBuilder::new(&self.executor)
+            .withdraw_from_account(
+                &Resource::Fungible {
+                    amount,
+                    resource_address,
+                },
+                from.account,
+            )
+            .call_method_with_all_resources(to, "deposit_batch")
+            .build(vec![from.key])
+            .unwrap();
+
+        self.run(TransactionKind::Fund, transaction)
+    }
+}
+
+/// A value that identifies an entity of the test environment, either by address or by
+/// a name registered in the test environment.
+pub trait ToAddress {
+    /// Returns the address of the entity.
+    fn to_address<L: SubstateStore>(&self, env: &TestEnv<L>) -> Address;
+}
+
+impl ToAddress for Address {
+    fn to_address<L: SubstateStore>(&self, _env: &TestEnv<L>) -> Address {
+        *self
+    }
+}
+
+impl ToAddress for &Address {
+    fn to_address<L: SubstateStore>(&self, _env: &TestEnv<L>) -> Address {
+        **self
+    }
+}
+
+impl ToAddress for &str {
+    fn to_address<L: SubstateStore>(&self, env: &TestEnv<L>) -> Address {
+        env.resolve(self)
+    }
+}
+
+impl ToAddress for String {
+    fn to_address<L: SubstateStore>(&self, env: &TestEnv<L>) -> Address {
+        env.resolve(self)
+    }
+}
+
+impl ToAddress for &String {
+    fn to_address<L: SubstateStore>(&self, env: &TestEnv<L>) -> Address {
+        env.resolve(self)
+    }
+}
+
+impl ToAddress for ResourceDef {
+    fn to_address<L: SubstateStore>(&self, _env: &TestEnv<L>) -> Address {
+        self.address()
+    }
+}
+
+impl ToAddress for &ResourceDef {
+    fn to_address<L: SubstateStore>(&self, _env: &TestEnv<L>) -> Address {
+        self.address()
+    }
+}
+
+impl ToAddress for Component {
+    fn to_address<L: SubstateStore>(&self, _env: &TestEnv<L>) -> Address {
+        self.address()
+    }
+}
+
+impl ToAddress for &Component {
+    fn to_address<L: SubstateStore>(&self, _env: &TestEnv<L>) -> Address {
+        self.address()
+    }
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum Contents {
     Amount(Decimal),
     NonFungibleKeys(Vec<NonFungibleKey>),
@@ -0,0 +1,223 @@
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use radix_engine::ledger::SubstateStore;
+use radix_engine::model::Receipt;
+use radix_engine::transaction::*;
+use scrypto::prelude::*;
+
+use crate::{TestEnv, TransactionKind};
+
+/// Splits a manifest into statements of words, keeping quoted strings together. Statements
+/// end with a `;` outside of quotes.
+fn tokenize(manifest: &str) -> Vec<Vec<String>> {
+    let mut statements = Vec::new();
+    let mut tokens = Vec::new();
+    let mut chars = manifest.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == ';' {
+            chars.next();
+            if !tokens.is_empty() {
+                statements.push(tokens);
+                tokens = Vec::new();
+            }
+        } else if c == '"' {
+            chars.next();
+            let mut token = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => token.push(c),
+                    None => panic!("Unterminated string in manifest after {:?}", tokens),
+                }
+            }
+            tokens.push(token);
+        } else {
+            let mut token = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == ';' {
+                    break;
+                }
+                token.push(c);
+                chars.next();
+            }
+            tokens.push(token);
+        }
+    }
+    if !tokens.is_empty() {
+        panic!("Manifest statement {:?} is missing its `;`", tokens.join(" "));
+    }
+
+    statements
+}
+
+fn parse_address(token: &str) -> Address {
+    match Address::from_str(token) {
+        Ok(address) => address,
+        Err(_) => panic!("Invalid address {:?} in manifest", token),
+    }
+}
+
+fn parse_decimal(token: &str) -> Decimal {
+    match Decimal::from_str(token) {
+        Ok(amount) => amount,
+        Err(_) => panic!("Invalid amount {:?} in manifest", token),
+    }
+}
+
+impl<'a, L: SubstateStore> TestEnv<'a, L> {
+    /// Replaces the placeholders of a manifest with addresses from the registries.
+    ///
+    /// Supported placeholders are `${user.account}`, `${pkg:name}`, `${component:name}`,
+    /// `${resource:name}` and `${name}`, the latter resolved like any registered name.
+    ///
+    /// # Arguments
+    ///
+    /// * `manifest` - The manifest text.
+    pub fn substitute_placeholders(&self, manifest: &str) -> String {
+        let mut output = String::new();
+        let mut rest = manifest;
+
+        while let Some(start) = rest.find("${") {
+            output.push_str(&rest[..start]);
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => panic!("Unterminated placeholder in manifest: {:?}", &rest[start..]),
+            };
+            let placeholder = &rest[start + 2..end];
+
+            let address = if let Some(name) = placeholder.strip_prefix("pkg:") {
+                self.get_package(name)
+            } else if let Some(name) = placeholder.strip_prefix("component:") {
+                self.get_component(name)
+            } else if let Some(name) = placeholder.strip_prefix("resource:") {
+                self.get_resource(name)
+            } else if let Some(name) = placeholder.strip_suffix(".account") {
+                self.get_user(name).account
+            } else {
+                self.resolve(placeholder)
+            };
+            output.push_str(&address.to_string());
+
+            rest = &rest[end + 1..];
+        }
+        output.push_str(rest);
+
+        output
+    }
+
+    /// Runs a text transaction manifest, signed by the current user.
+    ///
+    /// The radix engine this crate targets has no text manifest format, so this is a
+    /// bespoke subset of our own rather than the syntax of later Scrypto releases: manifests
+    /// written for those need translating before they can run here.
+    ///
+    /// Statements end with `;` outside of quotes and lines starting with `#` are comments,
+    /// removed before placeholders are substituted. The supported statements are:
+    ///
+    /// * `CALL_FUNCTION <package> "<blueprint>" "<function>" "<arg>"...;`
+    /// * `CALL_METHOD <component> "<method>" "<arg>"...;`
+    /// * `CALL_METHOD_WITH_ALL_RESOURCES <component> "<method>";`
+    /// * `WITHDRAW_FROM_ACCOUNT <account> "<amount>" <resource>;`
+    ///
+    /// Arguments are parsed against the blueprint ABI, with buckets withdrawn from the
+    /// current user's account.
+    ///
+    /// # Arguments
+    ///
+    /// * `manifest` - The manifest text, with placeholders.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    /// env.create_user("alice");
+    /// env.publish_package(
+    ///     "hello_world",
+    ///     include_code!("../tests/assets/hello-world", "hello_world")
+    /// );
+    ///
+    /// let receipt = env.run_manifest(r#"
+    ///     CALL_FUNCTION ${pkg:hello_world} "Hello" "new" "1";
+    ///     CALL_METHOD_WITH_ALL_RESOURCES ${alice.account} "deposit_batch";
+    /// "#);
+    /// assert!(receipt.result.is_ok(), "{}", env.display(&receipt));
+    /// ```
+    pub fn run_manifest(&mut self, manifest: &str) -> Receipt {
+        let user = self.get_current_user();
+        let manifest: String = manifest
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .collect::<Vec<&str>>()
+            .join("\n");
+        let manifest = self.substitute_placeholders(&manifest);
+
+        let mut builder = TransactionBuilder::new(&self.executor);
+        for tokens in tokenize(&manifest) {
+            match tokens[0].as_str() {
+                "CALL_FUNCTION" if tokens.len() >= 4 => {
+                    builder.call_function(
+                        parse_address(&tokens[1]),
+                        &tokens[2],
+                        &tokens[3],
+                        tokens[4..].to_vec(),
+                        Some(user.account),
+                    );
+                }
+                "CALL_METHOD" if tokens.len() >= 3 => {
+                    builder.call_method(
+                        parse_address(&tokens[1]),
+                        &tokens[2],
+                        tokens[3..].to_vec(),
+                        Some(user.account),
+                    );
+                }
+                "CALL_METHOD_WITH_ALL_RESOURCES" if tokens.len() == 3 => {
+                    builder.call_method_with_all_resources(parse_address(&tokens[1]), &tokens[2]);
+                }
+                "WITHDRAW_FROM_ACCOUNT" if tokens.len() == 4 => {
+                    builder.withdraw_from_account(
+                        &Resource::Fungible {
+                            amount: parse_decimal(&tokens[2]),
+                            resource_address: parse_address(&tokens[3]),
+                        },
+                        parse_address(&tokens[1]),
+                    );
+                }
+                _ => panic!("Invalid manifest statement {:?}", tokens.join(" ")),
+            }
+        }
+        let transaction = match builder.build(vec![user.key]) {
+            Ok(transaction) => transaction,
+            Err(error) => panic!("Failed to build manifest: {:?}", error),
+        };
+
+        let receipt = self.run(TransactionKind::Manifest, transaction);
+        self.register_new_entities(&receipt);
+
+        receipt
+    }
+
+    /// Runs a text transaction manifest read from a file, signed by the current user.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the manifest file.
+    pub fn run_manifest_file<P: AsRef<Path>>(&mut self, path: P) -> Receipt {
+        let manifest = match fs::read_to_string(path.as_ref()) {
+            Ok(manifest) => manifest,
+            Err(error) => panic!("Cannot read manifest {:?}: {}", path.as_ref(), error),
+        };
+
+        self.run_manifest(&manifest)
+    }
+}
@@ -0,0 +1,363 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use radix_engine::ledger::SubstateStore;
+use radix_engine::model::*;
+use scrypto::prelude::*;
+
+use crate::{TestEnv, TransactionKind};
+
+/// Substate access counters shared between a `MeteredSubstateStore` and a `TestEnv`.
+#[derive(Debug, Default)]
+pub struct SubstateCounters {
+    reads: Cell<u64>,
+    writes: Cell<u64>,
+    bytes_written: Cell<u64>,
+}
+
+impl SubstateCounters {
+    fn read(&self) {
+        self.reads.set(self.reads.get() + 1);
+    }
+
+    fn write<T: Encode>(&self, substate: &T) {
+        self.writes.set(self.writes.get() + 1);
+        let bytes = scrypto_encode(substate).len() as u64;
+        self.bytes_written.set(self.bytes_written.get() + bytes);
+    }
+
+    /// Returns the number of reads, writes and bytes written so far.
+    pub fn get(&self) -> (u64, u64, u64) {
+        (self.reads.get(), self.writes.get(), self.bytes_written.get())
+    }
+}
+
+/// A substate store counting the substates read and written through it.
+///
+/// # Examples
+/// ```
+/// use scrypto_unit::*;
+/// use radix_engine::ledger::*;
+/// use scrypto::prelude::*;
+///
+/// let mut ledger = MeteredSubstateStore::new(InMemorySubstateStore::with_bootstrap());
+/// let mut env = TestEnv::new_metered(&mut ledger);
+/// env.create_user("acc1");
+///
+/// let metrics = env.last_metrics();
+/// assert!(metrics.substate_writes.unwrap() > 0);
+/// ```
+pub struct MeteredSubstateStore<L: SubstateStore> {
+    inner: L,
+    counters: Rc<SubstateCounters>,
+}
+
+impl<L: SubstateStore> MeteredSubstateStore<L> {
+    /// Wraps a substate store.
+    pub fn new(inner: L) -> Self {
+        Self {
+            inner,
+            counters: Rc::new(SubstateCounters::default()),
+        }
+    }
+
+    /// Returns the counters of the store.
+    pub fn counters(&self) -> Rc<SubstateCounters> {
+        self.counters.clone()
+    }
+
+    /// Returns the wrapped substate store.
+    pub fn into_inner(self) -> L {
+        self.inner
+    }
+}
+
+impl<L: SubstateStore> SubstateStore for MeteredSubstateStore<L> {
+    fn get_resource_def(&self, address: Address) -> Option<ResourceDef> {
+        self.counters.read();
+        self.inner.get_resource_def(address)
+    }
+
+    fn put_resource_def(&mut self, address: Address, resource_def: ResourceDef) {
+        self.counters.write(&resource_def);
+        self.inner.put_resource_def(address, resource_def)
+    }
+
+    fn get_package(&self, address: Address) -> Option<Package> {
+        self.counters.read();
+        self.inner.get_package(address)
+    }
+
+    fn put_package(&mut self, address: Address, package: Package) {
+        self.counters.write(&package);
+        self.inner.put_package(address, package)
+    }
+
+    fn get_component(&self, address: Address) -> Option<Component> {
+        self.counters.read();
+        self.inner.get_component(address)
+    }
+
+    fn put_component(&mut self, address: Address, component: Component) {
+        self.counters.write(&component);
+        self.inner.put_component(address, component)
+    }
+
+    fn get_lazy_map(&self, component_address: &Address, mid: &Mid) -> Option<LazyMap> {
+        self.counters.read();
+        self.inner.get_lazy_map(component_address, mid)
+    }
+
+    fn put_lazy_map(&mut self, component_address: &Address, mid: &Mid, lazy_map: LazyMap) {
+        self.counters.write(&lazy_map);
+        self.inner.put_lazy_map(component_address, mid, lazy_map)
+    }
+
+    fn get_vault(&self, component_address: &Address, vid: &Vid) -> Option<Vault> {
+        self.counters.read();
+        self.inner.get_vault(component_address, vid)
+    }
+
+    fn put_vault(&mut self, component_address: &Address, vid: &Vid, vault: Vault) {
+        self.counters.write(&vault);
+        self.inner.put_vault(component_address, vid, vault)
+    }
+
+    fn get_non_fungible(
+        &self,
+        resource_address: Address,
+        key: &NonFungibleKey,
+    ) -> Option<NonFungible> {
+        self.counters.read();
+        self.inner.get_non_fungible(resource_address, key)
+    }
+
+    fn put_non_fungible(
+        &mut self,
+        resource_address: Address,
+        key: &NonFungibleKey,
+        non_fungible: NonFungible,
+    ) {
+        self.counters.write(&non_fungible);
+        self.inner.put_non_fungible(resource_address, key, non_fungible)
+    }
+
+    fn get_epoch(&self) -> u64 {
+        self.inner.get_epoch()
+    }
+
+    fn set_epoch(&mut self, epoch: u64) {
+        self.inner.set_epoch(epoch)
+    }
+
+    fn get_nonce(&self) -> u64 {
+        self.inner.get_nonce()
+    }
+
+    fn increase_nonce(&mut self) {
+        self.inner.increase_nonce()
+    }
+}
+
+/// What a transaction, or a group of transactions, cost to execute.
+///
+/// The engine does not meter wasm execution, so no wasm instruction count or fuel is
+/// available: the cost is given by the number of manifest instructions, wall time and,
+/// when the ledger is a `MeteredSubstateStore`, by substate accesses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExecutionMetrics {
+    /// The number of transactions measured.
+    pub transactions: usize,
+    /// The number of transaction manifest instructions, not wasm instructions.
+    pub manifest_instructions: usize,
+    /// The wall time spent in the executor.
+    pub elapsed: Duration,
+    /// The number of substates read, if the ledger is metered.
+    pub substate_reads: Option<u64>,
+    /// The number of substates written, if the ledger is metered.
+    pub substate_writes: Option<u64>,
+    /// The number of SBOR-encoded bytes written, if the ledger is metered.
+    pub bytes_written: Option<u64>,
+}
+
+fn add(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a + b),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+impl ExecutionMetrics {
+    /// Returns the sum of two measurements.
+    pub fn combine(&self, other: &ExecutionMetrics) -> ExecutionMetrics {
+        ExecutionMetrics {
+            transactions: self.transactions + other.transactions,
+            manifest_instructions: self.manifest_instructions + other.manifest_instructions,
+            elapsed: self.elapsed + other.elapsed,
+            substate_reads: add(self.substate_reads, other.substate_reads),
+            substate_writes: add(self.substate_writes, other.substate_writes),
+            bytes_written: add(self.bytes_written, other.bytes_written),
+        }
+    }
+
+    /// Checks the measurement against a budget, describing every exceeded limit.
+    ///
+    /// A limit on a quantity that was not measured is reported as exceeded, so a budget
+    /// on substate accesses requires a metered ledger.
+    pub fn check(&self, budget: &Budget) -> Result<(), String> {
+        let mut exceeded = Vec::new();
+
+        if let Some(limit) = budget.elapsed {
+            if self.elapsed > limit {
+                exceeded.push(format!("elapsed {:?} > {:?}", self.elapsed, limit));
+            }
+        }
+        let counters = [
+            (
+                "manifest instructions",
+                Some(self.manifest_instructions as u64),
+                budget.manifest_instructions,
+            ),
+            ("substate reads", self.substate_reads, budget.substate_reads),
+            ("substate writes", self.substate_writes, budget.substate_writes),
+            ("bytes written", self.bytes_written, budget.bytes_written),
+        ];
+        for (name, value, limit) in counters.iter() {
+            match (value, limit) {
+                (Some(value), Some(limit)) if value > limit => {
+                    exceeded.push(format!("{} {} > {}", name, value, limit))
+                }
+                (None, Some(_)) => exceeded.push(format!("{} not measured", name)),
+                _ => {}
+            }
+        }
+
+        match exceeded.is_empty() {
+            true => Ok(()),
+            false => Err(exceeded.join(", ")),
+        }
+    }
+}
+
+/// Upper limits on the cost of execution, checked with `assert_cost_below!`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Budget {
+    pub elapsed: Option<Duration>,
+    pub manifest_instructions: Option<u64>,
+    pub substate_reads: Option<u64>,
+    pub substate_writes: Option<u64>,
+    pub bytes_written: Option<u64>,
+}
+
+impl Budget {
+    /// Returns a budget without limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the wall time spent in the executor.
+    pub fn elapsed(mut self, limit: Duration) -> Self {
+        self.elapsed = Some(limit);
+        self
+    }
+
+    /// Limits the number of transaction manifest instructions.
+    pub fn manifest_instructions(mut self, limit: u64) -> Self {
+        self.manifest_instructions = Some(limit);
+        self
+    }
+
+    /// Limits the number of substates read.
+    pub fn substate_reads(mut self, limit: u64) -> Self {
+        self.substate_reads = Some(limit);
+        self
+    }
+
+    /// Limits the number of substates written.
+    pub fn substate_writes(mut self, limit: u64) -> Self {
+        self.substate_writes = Some(limit);
+        self
+    }
+
+    /// Limits the number of bytes written.
+    pub fn bytes_written(mut self, limit: u64) -> Self {
+        self.bytes_written = Some(limit);
+        self
+    }
+}
+
+/// Asserts that execution metrics, or the metrics of a `TransactionRecord`, are within a
+/// budget.
+///
+/// Receipts carry no metrics, so pass the record of the transaction from the history.
+///
+/// # Examples
+/// ```
+/// use scrypto_unit::*;
+/// use radix_engine::ledger::*;
+/// use scrypto::prelude::*;
+///
+/// let mut ledger = MeteredSubstateStore::new(InMemorySubstateStore::with_bootstrap());
+/// let mut env = TestEnv::new_metered(&mut ledger);
+/// env.create_user("acc1");
+///
+/// let (_, metrics) = env.measure(|env| env.create_token(10000.into()));
+/// assert_cost_below!(metrics, Budget::new().substate_writes(100).manifest_instructions(2));
+/// assert_cost_below!(env.history.last().unwrap(), Budget::new().substate_writes(100));
+/// ```
+#[macro_export]
+macro_rules! assert_cost_below {
+    ($metrics:expr, $budget:expr) => {
+        if let Err(exceeded) = $metrics.check(&$budget) {
+            panic!("Execution cost above budget: {} ({:?})", exceeded, $metrics);
+        }
+    };
+}
+
+impl<'a, I: SubstateStore> TestEnv<'a, MeteredSubstateStore<I>> {
+    /// Returns a test environment exactly like `new`, which also records substate accesses
+    /// in the execution metrics.
+    pub fn new_metered(ledger: &'a mut MeteredSubstateStore<I>) -> Self {
+        let counters = ledger.counters();
+        let mut env = Self::new(ledger);
+        env.counters = Some(counters);
+
+        env
+    }
+}
+
+impl<'a, L: SubstateStore> TestEnv<'a, L> {
+    /// Returns the execution metrics of the last transaction.
+    pub fn last_metrics(&self) -> ExecutionMetrics {
+        match self.history.last() {
+            Some(record) => record.metrics,
+            None => panic!("No transaction executed yet"),
+        }
+    }
+
+    /// Runs a closure, returning its result with the combined execution metrics of the
+    /// transactions it executed. Works with any of the call helpers.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The closure to measure.
+    pub fn measure<F, R>(&mut self, f: F) -> (R, ExecutionMetrics)
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let start = self.history.len();
+        let result = f(self);
+        let metrics = self
+            .history
+            .iter()
+            .skip(start)
+            .filter(|record| record.kind != TransactionKind::MockResponse)
+            .fold(ExecutionMetrics::default(), |total, record| {
+                total.combine(&record.metrics)
+            });
+
+        (result, metrics)
+    }
+}
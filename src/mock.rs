@@ -0,0 +1,233 @@
+use std::mem;
+
+use radix_engine::ledger::SubstateStore;
+use radix_engine::model::Transaction;
+use radix_engine::transaction::*;
+use sbor::{Decode, Encode, TypeId};
+use scrypto::prelude::*;
+
+use crate::{TestEnv, ToAddress, TransactionKind};
+
+/// A closure computing the SBOR-encoded response of a mocked method from the test
+/// environment, evaluated again before every transaction.
+pub type MockResponse<'a, L> = Box<dyn FnMut(&TestEnv<'a, L>) -> Vec<u8> + 'a>;
+
+/// A response set with `mock_response_with`, and the value last stored in the mock.
+pub(crate) struct DynamicResponse<'a, L: SubstateStore> {
+    mock: Address,
+    method: String,
+    response: MockResponse<'a, L>,
+    stored: Option<Vec<u8>>,
+}
+
+/// The state of a `Mock` component, as defined in `blueprints/src/mock.rs`.
+#[derive(TypeId, Encode, Decode)]
+struct MockState {
+    responses: HashMap<String, Vec<u8>>,
+    calls: Vec<(String, Vec<Vec<u8>>)>,
+}
+
+/// A call received by a mock component.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockCall {
+    /// The name of the method called.
+    pub method: String,
+    /// The SBOR-encoded arguments, without the component address.
+    pub args: Vec<Vec<u8>>,
+}
+
+impl MockCall {
+    /// Decodes an argument of the call.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The position of the argument.
+    pub fn arg<T: Decode>(&self, index: usize) -> T {
+        scrypto_decode(&self.args[index]).unwrap()
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl<'a, L: SubstateStore> TestEnv<'a, L> {
+    /// Instantiates a mock component from the generic blueprints shipped with this crate and
+    /// registers it under the given name.
+    ///
+    /// Any method can be called on a mock by other blueprints. It returns the response set
+    /// with `mock_response`, `mock_response_for` or `mock_response_with`, and panics when
+    /// none is set. The calls received are returned by `mock_calls`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name to register the component under, which must not be registered
+    ///   already. Like any registered component, the first one becomes the current component.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    /// env.create_user("acc1");
+    ///
+    /// let oracle = env.create_mock("oracle");
+    /// env.mock_response("oracle", "get_price", Decimal::from(42));
+    /// assert!(env.mock_calls(oracle).is_empty());
+    /// ```
+    pub fn create_mock(&mut self, name: &str) -> Address {
+        if self.components.contains_key(name) {
+            panic!("A component named {:?} is already registered", name);
+        }
+
+        let package = self.blueprints_package();
+        let transaction = TransactionBuilder::new(&self.executor)
+            .call_function(package, "Mock", "new", Vec::new(), None)
+            .build(Vec::new())
+            .unwrap();
+        let receipt = self.run(TransactionKind::CallFunction, transaction);
+        let mock = match receipt.component(0) {
+            Some(mock) => mock,
+            None => panic!("Failed to create mock {:?}:\n{}", name, self.display(&receipt)),
+        };
+        self.register_component(name, mock);
+
+        mock
+    }
+
+    /// Sets the response of a method of a mock component, whatever its arguments.
+    ///
+    /// # Arguments
+    ///
+    /// * `mock` - The address or registered name of the mock.
+    /// * `method` - The name of the method.
+    /// * `value` - The value returned by the method.
+    pub fn mock_response<T: Encode>(
+        &mut self,
+        mock: impl ToAddress,
+        method: &str,
+        value: T,
+    ) -> &mut Self {
+        let mock = mock.to_address(self);
+        self.set_mock_response(mock, method.to_owned(), scrypto_encode(&value));
+
+        self
+    }
+
+    /// Sets the response of a method of a mock component when called with the given
+    /// arguments, which takes precedence over the one set by `mock_response`.
+    ///
+    /// # Arguments
+    ///
+    /// * `mock` - The address or registered name of the mock.
+    /// * `method` - The name of the method.
+    /// * `args` - The SBOR-encoded arguments.
+    /// * `value` - The value returned by the method.
+    pub fn mock_response_for<T: Encode>(
+        &mut self,
+        mock: impl ToAddress,
+        method: &str,
+        args: Vec<Vec<u8>>,
+        value: T,
+    ) -> &mut Self {
+        let mock = mock.to_address(self);
+        let key = format!("{}:{}", method, to_hex(&scrypto_encode(&args)));
+        self.set_mock_response(mock, key, scrypto_encode(&value));
+
+        self
+    }
+
+    /// Sets the response of a method of a mock component to the value computed by a closure,
+    /// evaluated again before every transaction so it can follow the state of the test.
+    ///
+    /// Whenever the value changes, it is stored by a transaction of its own, recorded in the
+    /// history as `TransactionKind::MockResponse` and excluded from metrics, coverage,
+    /// invariants and conservation checks.
+    ///
+    /// Blueprints run in wasm and cannot call back into the test, so the closure does not
+    /// see the arguments of the call, use `mock_response_for` to vary on them.
+    ///
+    /// # Arguments
+    ///
+    /// * `mock` - The address or registered name of the mock.
+    /// * `method` - The name of the method.
+    /// * `response` - Returns the value returned by the method.
+    pub fn mock_response_with<T: Encode, F: FnMut(&TestEnv<'a, L>) -> T + 'a>(
+        &mut self,
+        mock: impl ToAddress,
+        method: &str,
+        mut response: F,
+    ) -> &mut Self {
+        let mock = mock.to_address(self);
+        let encoded: MockResponse<'a, L> = Box::new(move |env| scrypto_encode(&response(env)));
+        self.mock_responses.push(DynamicResponse {
+            mock,
+            method: method.to_owned(),
+            response: encoded,
+            stored: None,
+        });
+
+        self
+    }
+
+    /// Returns the calls received by a mock component, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `mock` - The address or registered name of the mock.
+    pub fn mock_calls(&self, mock: impl ToAddress) -> Vec<MockCall> {
+        let mock = mock.to_address(self);
+        let component = self.executor.ledger().get_component(mock).unwrap();
+        let state: MockState = scrypto_decode(component.state()).unwrap();
+
+        state
+            .calls
+            .into_iter()
+            .map(|(method, args)| MockCall { method, args })
+            .collect()
+    }
+
+    /// Evaluates the closures set with `mock_response_with` and stores the responses that
+    /// changed.
+    ///
+    /// The closures are taken out of the test environment meanwhile, so running the
+    /// transactions does not refresh the responses again.
+    pub(crate) fn refresh_mock_responses(&mut self) {
+        let mut responses = mem::take(&mut self.mock_responses);
+        for dynamic in responses.iter_mut() {
+            let value = (dynamic.response)(self);
+            if dynamic.stored.as_ref() != Some(&value) {
+                self.set_mock_response(dynamic.mock, dynamic.method.clone(), value.clone());
+                dynamic.stored = Some(value);
+            }
+        }
+        self.mock_responses = responses;
+    }
+
+    fn set_mock_response(&mut self, mock: Address, key: String, value: Vec<u8>) {
+        let transaction = self.set_response_transaction(mock, key.clone(), value);
+        let receipt = self.run(TransactionKind::MockResponse, transaction);
+        if receipt.result.is_err() {
+            panic!(
+                "Failed to set the response of {}:\n{}",
+                key,
+                self.display(&receipt)
+            );
+        }
+    }
+
+    fn set_response_transaction(
+        &self,
+        mock: Address,
+        key: String,
+        value: Vec<u8>,
+    ) -> Transaction {
+        TransactionBuilder::new(&self.executor)
+            .call_method(mock, "set_response", vec![key, to_hex(&value)], None)
+            .build(Vec::new())
+            .unwrap()
+    }
+}
@@ -0,0 +1,275 @@
+use std::env;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+use radix_engine::ledger::SubstateStore;
+use scrypto::prelude::*;
+
+use crate::fixture::Fixture;
+use crate::TestEnv;
+
+/// The environment variable that, when set, makes `Scenario::check` only run that seed.
+pub const SEED_VAR: &str = "SCRYPTO_UNIT_SEED";
+
+/// A small deterministic random number generator (SplitMix64).
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Returns a generator seeded with the given value.
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Returns the next random number.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a random number in `0..n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "Cannot pick a number below 0");
+        self.next_u64() % n
+    }
+
+    /// Returns a random element of a slice.
+    pub fn pick<'t, T>(&mut self, items: &'t [T]) -> &'t T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+
+    /// Returns a random whole amount in `0..=max`.
+    pub fn amount(&mut self, max: u64) -> Decimal {
+        Decimal::from(self.below(max.saturating_add(1).max(1)))
+    }
+
+    /// Returns the name of a random user of the test environment.
+    pub fn user<L: SubstateStore>(&mut self, env: &TestEnv<L>) -> String {
+        let mut names: Vec<&String> = env.users.keys().collect();
+        names.sort();
+        self.pick(&names).to_string()
+    }
+}
+
+/// One step of a scenario: an action, and the seed of the generator it is run with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    pub action: usize,
+    pub seed: u64,
+}
+
+/// A failing scenario, shrunk to a minimal reproducing sequence of steps.
+#[derive(Debug, Clone)]
+pub struct ScenarioFailure {
+    /// The seed the original sequence was generated from.
+    pub seed: u64,
+    /// The action names and seeds of the minimal sequence.
+    pub steps: Vec<(String, u64)>,
+    /// The error or panic message of the last step.
+    pub message: String,
+}
+
+impl fmt::Display for ScenarioFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Scenario failed with seed {} (rerun with {}={}), minimal sequence:",
+            self.seed, SEED_VAR, self.seed
+        )?;
+        for (i, (name, seed)) in self.steps.iter().enumerate() {
+            writeln!(f, "  [{}] {} (seed {})", i, name, seed)?;
+        }
+        write!(f, "Error: {}", self.message)
+    }
+}
+
+type Action<L> = Box<dyn Fn(&mut TestEnv<'_, L>, &mut Rng) -> Result<(), String>>;
+
+/// A randomized scenario: named actions run in random sequences against fresh test
+/// environments, with failing sequences shrunk to a minimal reproduction.
+///
+/// An action fails by returning an error or panicking, which includes the invariants and
+/// conservation checks registered by the setup.
+///
+/// # Examples
+/// ```
+/// use scrypto_unit::*;
+/// use radix_engine::ledger::*;
+/// use scrypto::prelude::*;
+///
+/// let mut scenario = Scenario::new(InMemorySubstateStore::with_bootstrap, |env| {
+///     env.create_user("alice");
+///     env.create_user("bob");
+/// });
+/// scenario.action("transfer", |env, rng| {
+///     let from = rng.user(env);
+///     let to = rng.user(env);
+///     let amount = rng.amount(1000);
+///     let to = *env.get_user(&to);
+///     env.as_user(&from, |env| env.transfer_resource(amount, RADIX_TOKEN, &to));
+///     Ok(())
+/// });
+///
+/// scenario.check(5, 10);
+/// ```
+pub struct Scenario<L: SubstateStore> {
+    fixture: Fixture<L>,
+    actions: Vec<(String, Action<L>)>,
+}
+
+impl<L: SubstateStore> Scenario<L> {
+    /// Returns a scenario without actions.
+    ///
+    /// # Arguments
+    ///
+    /// * `ledger` - Creates the fresh ledger of each run.
+    /// * `setup` - Prepares the test environment of each run.
+    pub fn new<M, S>(ledger: M, setup: S) -> Self
+    where
+        M: Fn() -> L + 'static,
+        S: Fn(&mut TestEnv<'_, L>) + 'static,
+    {
+        Self {
+            fixture: Fixture::new(ledger, setup),
+            actions: Vec::new(),
+        }
+    }
+
+    /// Adds a named action, run with a generator to draw its arguments from.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the action, used in reports.
+    /// * `action` - The action.
+    pub fn action<F>(&mut self, name: &str, action: F) -> &mut Self
+    where
+        F: Fn(&mut TestEnv<'_, L>, &mut Rng) -> Result<(), String> + 'static,
+    {
+        self.actions.push((String::from(name), Box::new(action)));
+
+        self
+    }
+
+    /// Generates the sequence of steps for a seed.
+    pub fn generate(&self, seed: u64, length: usize) -> Vec<Step> {
+        assert!(!self.actions.is_empty(), "Scenario has no actions");
+        let mut rng = Rng::new(seed);
+
+        (0..length)
+            .map(|_| Step {
+                action: rng.below(self.actions.len() as u64) as usize,
+                seed: rng.next_u64(),
+            })
+            .collect()
+    }
+
+    /// Runs a sequence of steps against a fresh test environment, returning the index and
+    /// message of the failing step, if any.
+    pub fn execute(&self, steps: &[Step]) -> Result<(), (usize, String)> {
+        let mut ledger = self.fixture.ledger();
+        let mut env = TestEnv::new(&mut ledger);
+        self.fixture.setup(&mut env);
+
+        for (i, step) in steps.iter().enumerate() {
+            let action = &self.actions[step.action].1;
+            let mut rng = Rng::new(step.seed);
+
+            match panic::catch_unwind(AssertUnwindSafe(|| action(&mut env, &mut rng))) {
+                Ok(Ok(())) => {}
+                Ok(Err(message)) => return Err((i, message)),
+                Err(payload) => return Err((i, panic_message(payload))),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes steps from a failing sequence for as long as it keeps failing.
+    fn shrink(&self, mut steps: Vec<Step>, mut message: String) -> (Vec<Step>, String) {
+        let mut chunk = (steps.len() / 2).max(1);
+
+        while chunk > 0 {
+            let mut removed = false;
+            let mut i = 0;
+            while i < steps.len() {
+                let end = (i + chunk).min(steps.len());
+                let candidate: Vec<Step> =
+                    steps[..i].iter().chain(&steps[end..]).copied().collect();
+                match self.execute(&candidate) {
+                    Err((failed, m)) => {
+                        steps = candidate[..=failed].to_vec();
+                        message = m;
+                        removed = true;
+                    }
+                    Ok(()) => i += chunk,
+                }
+            }
+            if !removed {
+                chunk /= 2;
+            }
+        }
+
+        (steps, message)
+    }
+
+    /// Runs the sequence generated from a seed, shrinking it on failure.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed of the sequence.
+    /// * `length` - The number of steps.
+    pub fn run(&self, seed: u64, length: usize) -> Result<(), ScenarioFailure> {
+        let steps = self.generate(seed, length);
+
+        match self.execute(&steps) {
+            Ok(()) => Ok(()),
+            Err((failed, message)) => {
+                let (steps, message) = self.shrink(steps[..=failed].to_vec(), message);
+                Err(ScenarioFailure {
+                    seed,
+                    steps: steps
+                        .iter()
+                        .map(|step| (self.actions[step.action].0.clone(), step.seed))
+                        .collect(),
+                    message,
+                })
+            }
+        }
+    }
+
+    /// Runs the sequences generated from seeds `0..runs`, or only from the seed in
+    /// `SCRYPTO_UNIT_SEED` when set, panicking with the minimal failing sequence.
+    ///
+    /// # Arguments
+    ///
+    /// * `runs` - The number of sequences to run.
+    /// * `length` - The number of steps of each sequence.
+    pub fn check(&self, runs: u64, length: usize) {
+        let seeds: Vec<u64> = match env::var(SEED_VAR) {
+            Ok(seed) => vec![seed.parse().expect("Invalid SCRYPTO_UNIT_SEED")],
+            Err(_) => (0..runs).collect(),
+        };
+
+        for seed in seeds {
+            if let Err(failure) = self.run(seed, length) {
+                panic!("{}", failure);
+            }
+        }
+    }
+}
+
+/// Extracts the message of a panic payload.
+pub(crate) fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => String::from("<non-string panic>"),
+        },
+    }
+}
@@ -0,0 +1,241 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use radix_engine::ledger::SubstateStore;
+use radix_engine::model::{Instruction, Transaction};
+use sbor::{Decode, Encode, TypeId};
+use scrypto::prelude::*;
+
+use crate::{TestEnv, TransactionKind, User};
+
+/// A transaction of a recorded session, with the outcome observed when it was recorded.
+#[derive(Debug, Clone, TypeId, Encode, Decode)]
+pub struct SessionTransaction {
+    /// The helper the transaction was executed through.
+    pub kind: TransactionKind,
+    /// The transaction as submitted to the executor.
+    pub transaction: Transaction,
+    /// Whether the transaction succeeded.
+    pub success: bool,
+    /// The SBOR-encoded output of each instruction.
+    pub outputs: Vec<Vec<u8>>,
+}
+
+/// A `TestEnv` session: its named entities and every transaction it executed, in order.
+///
+/// Replaying a session into an environment over a freshly bootstrapped ledger reproduces the
+/// same addresses, so the registries can be restored as they were.
+#[derive(Debug, Clone, TypeId, Encode, Decode)]
+pub struct Session {
+    /// The users by name, with their key and account.
+    pub users: Vec<(String, EcdsaPublicKey, Address)>,
+    /// The packages by name, with their address and the hash of their code.
+    pub packages: Vec<(String, Address, H256)>,
+    /// The named components.
+    pub components: Vec<(String, Address)>,
+    /// The named resources.
+    pub resources: Vec<(String, Address)>,
+    /// The name of the current user, if any.
+    pub current_user: Option<String>,
+    /// The current package, if any.
+    pub current_package: Option<Address>,
+    /// The current component, if any.
+    pub current_component: Option<Address>,
+    /// The key and account of the treasury, if it was created.
+    pub treasury: Option<(EcdsaPublicKey, Address)>,
+    /// The published versions of each package, oldest first.
+    pub package_versions: Vec<(String, Vec<String>)>,
+    /// The components and resources only registered under a generated name.
+    pub generated_names: Vec<Address>,
+    /// The executed transactions, oldest first.
+    pub transactions: Vec<SessionTransaction>,
+}
+
+impl Session {
+    /// Writes the session to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, scrypto_encode(self))
+    }
+
+    /// Reads a session from a file written by `save`.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        scrypto_decode(&bytes).map_err(|error| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", error))
+        })
+    }
+}
+
+impl<'a, L: SubstateStore> TestEnv<'a, L> {
+    /// Exports the named entities and the transaction history of the test environment.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    /// env.create_user("acc1");
+    /// env.publish_package(
+    ///     "package",
+    ///     include_code!("../tests/assets/hello-world", "hello_world")
+    /// );
+    /// env.call_function("Hello", "new", vec!["1".to_owned()]);
+    ///
+    /// let session = env.export_session();
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut replayed = TestEnv::new(&mut ledger);
+    /// replayed.replay_session(&session).unwrap();
+    /// assert_eq!(replayed.get_user("acc1"), env.get_user("acc1"));
+    /// ```
+    pub fn export_session(&self) -> Session {
+        let code_hashes = self.code_hashes();
+
+        Session {
+            users: self
+                .users
+                .iter()
+                .map(|(name, user)| (name.clone(), user.key, user.account))
+                .collect(),
+            packages: self
+                .packages
+                .iter()
+                .map(|(name, package)| {
+                    let hash = match code_hashes.get(package) {
+                        Some(&hash) => hash,
+                        None => panic!("Package {:?} was not published in this session", name),
+                    };
+                    (name.clone(), *package, hash)
+                })
+                .collect(),
+            components: self
+                .components
+                .iter()
+                .map(|(name, address)| (name.clone(), *address))
+                .collect(),
+            resources: self
+                .resources
+                .iter()
+                .map(|(name, address)| (name.clone(), *address))
+                .collect(),
+            current_user: self.current_user.and_then(|user| self.lookup_key(&user.key)),
+            current_package: self.current_package,
+            current_component: self.current_component,
+            treasury: self.treasury.map(|user| (user.key, user.account)),
+            package_versions: self
+                .package_versions
+                .iter()
+                .map(|(name, versions)| (name.clone(), versions.clone()))
+                .collect(),
+            generated_names: self.generated_names.iter().cloned().collect(),
+            transactions: self
+                .history
+                .iter()
+                .map(|record| SessionTransaction {
+                    kind: record.kind,
+                    transaction: record.transaction.clone(),
+                    success: record.is_success(),
+                    outputs: record.outputs.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Replays a recorded session, checking that every transaction has the same outcome and
+    /// outputs as when it was recorded and that packages have the same code, then restores
+    /// the session's named entities and the current user, package and component.
+    ///
+    /// The test environment is expected to run over a freshly bootstrapped ledger.
+    ///
+    /// # Arguments
+    ///
+    /// * `session` - The session to replay.
+    pub fn replay_session(&mut self, session: &Session) -> Result<(), String> {
+        for (i, recorded) in session.transactions.iter().enumerate() {
+            let receipt = self.run(recorded.kind, recorded.transaction.clone());
+
+            if receipt.result.is_ok() != recorded.success {
+                return Err(format!(
+                    "Transaction {} ({:?}) was expected to {}:\n{}",
+                    i,
+                    recorded.kind,
+                    if recorded.success { "succeed" } else { "fail" },
+                    self.display(&receipt)
+                ));
+            }
+            let outputs: Vec<Vec<u8>> = receipt.outputs.iter().map(|o| o.raw.clone()).collect();
+            if outputs != recorded.outputs {
+                return Err(format!(
+                    "Transaction {} ({:?}) returned different outputs:\n{}",
+                    i,
+                    recorded.kind,
+                    self.display(&receipt)
+                ));
+            }
+        }
+
+        for (name, key, account) in &session.users {
+            self.users.insert(
+                name.clone(),
+                User {
+                    key: *key,
+                    account: *account,
+                },
+            );
+        }
+        let code_hashes = self.code_hashes();
+        for (name, package, hash) in &session.packages {
+            if code_hashes.get(package) != Some(hash) {
+                return Err(format!(
+                    "Package {:?} was not published with the recorded code",
+                    name
+                ));
+            }
+            self.packages.insert(name.clone(), *package);
+        }
+        for (name, versions) in &session.package_versions {
+            self.package_versions.insert(name.clone(), versions.clone());
+        }
+        for (name, component) in &session.components {
+            self.components.insert(name.clone(), *component);
+        }
+        for (name, resource) in &session.resources {
+            self.resources.insert(name.clone(), *resource);
+        }
+        self.generated_names = session.generated_names.iter().cloned().collect();
+        if let Some(name) = &session.current_user {
+            self.acting_as(name);
+        }
+        self.current_package = session.current_package;
+        self.current_component = session.current_component;
+        self.treasury = session
+            .treasury
+            .map(|(key, account)| User { key, account });
+
+        Ok(())
+    }
+
+    /// Returns the hash of the code of every package published through the test environment.
+    fn code_hashes(&self) -> HashMap<Address, H256> {
+        self.history
+            .iter()
+            .filter(|record| record.kind == TransactionKind::PublishPackage)
+            .filter_map(|record| {
+                let package = record.new_entities.first()?;
+                record
+                    .transaction
+                    .instructions
+                    .iter()
+                    .find_map(|instruction| match instruction {
+                        Instruction::PublishPackage { code } => Some((*package, sha256(code))),
+                        _ => None,
+                    })
+            })
+            .collect()
+    }
+}
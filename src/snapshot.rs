@@ -0,0 +1,309 @@
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use radix_engine::engine::validate_data;
+use radix_engine::ledger::SubstateStore;
+use scrypto::prelude::*;
+
+use crate::{Contents, TestEnv};
+
+/// The environment variable that, when set, makes `assert_snapshot!` overwrite snapshots.
+pub const BLESS_VAR: &str = "SCRYPTO_UNIT_BLESS";
+
+/// The environment variable set by CI services, which makes missing snapshots fail.
+const CI_VAR: &str = "CI";
+
+/// The minimum length of a hexadecimal run rendered as an address or id, shorter than any
+/// address (27 bytes) or hash (32 bytes) but longer than amounts and keys of sample data.
+const ID_HEX_LEN: usize = 40;
+
+/// Asserts that the normalized state of a `TestEnv` matches the snapshot with the given
+/// name, stored in a `snapshots` directory next to the calling test file, or in the given
+/// directory.
+///
+/// A missing snapshot is written and passes, unless `CI` is set, in which case it fails.
+/// Snapshots are rewritten when `SCRYPTO_UNIT_BLESS` is set.
+///
+/// # Examples
+/// ```no_run
+/// use scrypto_unit::*;
+/// use radix_engine::ledger::*;
+/// use scrypto::prelude::*;
+///
+/// let mut ledger = InMemorySubstateStore::with_bootstrap();
+/// let mut env = TestEnv::new(&mut ledger);
+/// env.create_user("alice");
+///
+/// assert_snapshot!(env, "after_create_user");
+/// ```
+#[macro_export]
+macro_rules! assert_snapshot {
+    ($env:expr, $name:expr) => {
+        $crate::assert_snapshot_in(
+            &$crate::snapshot_dir(env!("CARGO_MANIFEST_DIR"), file!()),
+            $name,
+            &$env.snapshot(),
+        )
+    };
+    ($env:expr, $name:expr, $dir:expr) => {
+        $crate::assert_snapshot_in(::std::path::Path::new(&$dir), $name, &$env.snapshot())
+    };
+}
+
+/// How snapshots are checked, usually read from the environment with `from_env`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SnapshotMode {
+    /// Whether snapshots are rewritten instead of compared, set by `SCRYPTO_UNIT_BLESS`.
+    pub bless: bool,
+    /// Whether a missing snapshot fails instead of being written, set by `CI`.
+    pub ci: bool,
+}
+
+impl SnapshotMode {
+    /// Returns the mode set by the `SCRYPTO_UNIT_BLESS` and `CI` environment variables.
+    pub fn from_env() -> Self {
+        Self {
+            bless: env::var_os(BLESS_VAR).is_some(),
+            ci: env::var_os(CI_VAR).is_some(),
+        }
+    }
+}
+
+/// Returns the `snapshots` directory next to a source file, as named by `file!()`.
+///
+/// `file!()` is relative to the workspace root, which may be any ancestor of the crate
+/// directory, so the first ancestor under which the file exists is used.
+pub fn snapshot_dir(manifest_dir: &str, file: &str) -> PathBuf {
+    let source = Path::new(manifest_dir)
+        .ancestors()
+        .map(|dir| dir.join(file))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| Path::new(manifest_dir).join(file));
+
+    match source.parent() {
+        Some(dir) => dir.join("snapshots"),
+        None => PathBuf::from("snapshots"),
+    }
+}
+
+/// Compares a rendered snapshot with the file `<dir>/<name>.snap`, panicking with a diff
+/// when they differ.
+///
+/// A missing snapshot is written, unless `CI` is set, in which case this panics. The
+/// snapshot is rewritten when `SCRYPTO_UNIT_BLESS` is set.
+pub fn assert_snapshot_in(dir: &Path, name: &str, actual: &str) {
+    if let Err(message) = check_snapshot_in(dir, name, actual, SnapshotMode::from_env()) {
+        panic!("{}", message);
+    }
+}
+
+/// Compares a rendered snapshot with the file `<dir>/<name>.snap`, see `assert_snapshot_in`,
+/// in the given mode instead of the one set by the environment.
+///
+/// # Arguments
+///
+/// * `dir` - The directory of the snapshot.
+/// * `name` - The name of the snapshot.
+/// * `actual` - The rendered snapshot.
+/// * `mode` - Whether to rewrite the snapshot, and whether a missing one fails.
+pub fn check_snapshot_in(
+    dir: &Path,
+    name: &str,
+    actual: &str,
+    mode: SnapshotMode,
+) -> Result<(), String> {
+    let path = dir.join(format!("{}.snap", name));
+
+    if !mode.bless && !path.exists() && mode.ci {
+        return Err(format!(
+            "Snapshot {:?} is missing, run the test locally or with {} set to write it",
+            path, BLESS_VAR
+        ));
+    }
+    if mode.bless || !path.exists() {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(&path, actual).unwrap();
+        return Ok(());
+    }
+
+    let expected = fs::read_to_string(&path).unwrap();
+    match expected == actual {
+        true => Ok(()),
+        false => Err(format!(
+            "Snapshot {:?} does not match, set {} to update it:\n{}",
+            path,
+            BLESS_VAR,
+            diff(&expected, actual)
+        )),
+    }
+}
+
+/// Renders the lines that differ between two texts, prefixed with `-` and `+`.
+pub(crate) fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let mut output = String::new();
+
+    for i in 0..expected.len().max(actual.len()) {
+        match (expected.get(i), actual.get(i)) {
+            (Some(e), Some(a)) if e == a => {}
+            (e, a) => {
+                if let Some(e) = e {
+                    writeln!(output, "{:>4} - {}", i + 1, e).unwrap();
+                }
+                if let Some(a) = a {
+                    writeln!(output, "{:>4} + {}", i + 1, a).unwrap();
+                }
+            }
+        }
+    }
+
+    output
+}
+
+impl<'a, L: SubstateStore> TestEnv<'a, L> {
+    fn write_holdings(&self, output: &mut String, address: Address) {
+        let mut holdings: Vec<(String, Contents)> = self
+            .get_holdings(address)
+            .into_iter()
+            .map(|(resource, contents)| (self.name_or_address(&resource), contents))
+            .collect();
+        holdings.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (resource, contents) in holdings {
+            match contents {
+                Contents::Amount(amount) => writeln!(output, "  {}: {}", resource, amount),
+                Contents::NonFungibleKeys(mut keys) => {
+                    keys.sort_by_key(|key| key.to_string());
+                    let keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+                    writeln!(output, "  {}: [{}]", resource, keys.join(", "))
+                }
+            }
+            .unwrap();
+        }
+    }
+
+    /// Replaces the addresses and ids in a rendered snapshot, whose values depend on the
+    /// transaction hashes, with registered names or `#n` placeholders numbered in order of
+    /// first appearance.
+    fn normalize(&self, text: &str) -> String {
+        let mut output = String::new();
+        let mut placeholders: Vec<String> = Vec::new();
+        let mut rest = text;
+
+        while let Some(start) = rest.find(|c: char| c.is_ascii_hexdigit()) {
+            output.push_str(&rest[..start]);
+            let run = &rest[start..];
+            let len = run
+                .find(|c: char| !c.is_ascii_hexdigit())
+                .unwrap_or(run.len());
+            let hex = &run[..len];
+            let preceded_by_word = output
+                .chars()
+                .last()
+                .map_or(false, |c| c.is_alphanumeric() || c == '_');
+
+            if len < ID_HEX_LEN || preceded_by_word {
+                output.push_str(hex);
+            } else {
+                let name = Address::from_str(hex)
+                    .ok()
+                    .and_then(|address| self.lookup_name(&address));
+                match name {
+                    Some(name) => output.push_str(&name),
+                    None => {
+                        let n = match placeholders.iter().position(|p| p == hex) {
+                            Some(n) => n,
+                            None => {
+                                placeholders.push(hex.to_owned());
+                                placeholders.len() - 1
+                            }
+                        };
+                        write!(output, "#{}", n).unwrap();
+                    }
+                }
+            }
+            rest = &run[len..];
+        }
+        output.push_str(rest);
+
+        output
+    }
+
+    fn name_or_address(&self, address: &Address) -> String {
+        match self.lookup_name(address) {
+            Some(name) => name,
+            None => address.to_string(),
+        }
+    }
+
+    /// Renders a normalized view of the test environment: the holdings of registered users
+    /// and components, decoded component states, registered resources and the outcome of
+    /// the last transaction.
+    ///
+    /// Registered addresses are replaced by their names, and other addresses and ids, such
+    /// as those of vaults and lazy maps, by `#n` placeholders numbered in order of first
+    /// appearance, so the snapshot does not depend on transaction hashes.
+    pub fn snapshot(&self) -> String {
+        let mut output = String::new();
+
+        let mut users: Vec<(&String, Address)> = self
+            .users
+            .iter()
+            .map(|(name, user)| (name, user.account))
+            .collect();
+        users.sort_by(|a, b| a.0.cmp(b.0));
+        writeln!(output, "[users]").unwrap();
+        for (name, account) in users {
+            writeln!(output, "{}", name).unwrap();
+            self.write_holdings(&mut output, account);
+        }
+
+        let mut components: Vec<(&String, &Address)> = self.components.iter().collect();
+        components.sort_by(|a, b| a.0.cmp(b.0));
+        writeln!(output, "[components]").unwrap();
+        for (name, address) in components {
+            let state = match self.executor.ledger().get_component(*address) {
+                Some(component) => format!("{:?}", validate_data(component.state()).unwrap()),
+                None => String::from("<missing>"),
+            };
+            writeln!(output, "{} = {}", name, state).unwrap();
+            self.write_holdings(&mut output, *address);
+        }
+
+        let mut resources: Vec<(&String, &Address)> = self.resources.iter().collect();
+        resources.sort_by(|a, b| a.0.cmp(b.0));
+        writeln!(output, "[resources]").unwrap();
+        for (name, address) in resources {
+            match self.executor.ledger().get_resource_def(*address) {
+                Some(resource_def) => {
+                    writeln!(output, "{}: supply {}", name, resource_def.total_supply())
+                }
+                None => writeln!(output, "{}: <missing>", name),
+            }
+            .unwrap();
+        }
+
+        if let Some(record) = self.history.last() {
+            writeln!(output, "[last transaction]").unwrap();
+            match &record.result {
+                Ok(()) => writeln!(output, "result: success").unwrap(),
+                Err(error) => writeln!(output, "result: failure {}", error).unwrap(),
+            }
+            for instruction in &record.instructions {
+                writeln!(output, "  {}", instruction).unwrap();
+            }
+            for address in &record.new_entities {
+                writeln!(output, "created: {}", self.name_or_address(address)).unwrap();
+            }
+            for log in &record.logs {
+                writeln!(output, "log: {}", log).unwrap();
+            }
+        }
+
+        self.normalize(&output)
+    }
+}
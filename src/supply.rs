@@ -0,0 +1,124 @@
+use radix_engine::ledger::SubstateStore;
+use scrypto::prelude::*;
+
+use crate::{Contents, TestEnv, ToAddress};
+
+impl<'a, L: SubstateStore> TestEnv<'a, L> {
+    /// Returns the system component and every component created through the test
+    /// environment, which together own every vault on a ledger only driven by it.
+    pub(crate) fn known_components(&self) -> Vec<Address> {
+        let mut components = vec![SYSTEM_COMPONENT];
+        for record in self.history.iter() {
+            for address in &record.new_entities {
+                if address.is_component() && !components.contains(address) {
+                    components.push(*address);
+                }
+            }
+        }
+
+        components
+    }
+
+    /// Returns the resources created through the test environment, plus XRD.
+    fn known_resources(&self) -> Vec<Address> {
+        let mut resources = vec![RADIX_TOKEN];
+        for record in self.history.iter() {
+            for address in &record.new_entities {
+                if address.is_resource_def() && !resources.contains(address) {
+                    resources.push(*address);
+                }
+            }
+        }
+
+        resources
+    }
+
+    /// Returns the total amount of a resource held across the vaults of every account and
+    /// component, including vaults in nested lazy maps.
+    ///
+    /// Only components created through the test environment are visited, so transactions
+    /// submitted directly to `executor` are not accounted for.
+    ///
+    /// # Arguments
+    ///
+    /// * `resource_def` - The address or registered name of the resource.
+    ///
+    /// # Examples
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new(&mut ledger);
+    /// env.create_user("acc1");
+    /// let token = env.create_token(10000.into());
+    ///
+    /// assert_eq!(env.total_held(&token), 10000.into());
+    /// assert_eq!(env.total_held(&token), env.total_supply(&token));
+    /// ```
+    pub fn total_held(&self, resource_def: impl ToAddress) -> Decimal {
+        let resource_def = resource_def.to_address(self);
+        let mut total = Decimal::from(0);
+
+        for component in self.known_components() {
+            match self.get_holdings(component).remove(&resource_def) {
+                Some(Contents::Amount(amount)) => total = total + amount,
+                Some(Contents::NonFungibleKeys(keys)) => {
+                    total = total + Decimal::from(keys.len() as u64)
+                }
+                None => {}
+            }
+        }
+
+        total
+    }
+
+    /// Returns the total supply of a resource, according to its resource definition.
+    ///
+    /// # Arguments
+    ///
+    /// * `resource_def` - The address or registered name of the resource.
+    pub fn total_supply(&self, resource_def: impl ToAddress) -> Decimal {
+        let resource_def = resource_def.to_address(self);
+        match self.executor.ledger().get_resource_def(resource_def) {
+            Some(resource_def) => resource_def.total_supply(),
+            None => panic!("No resource definition found at {}", resource_def),
+        }
+    }
+
+    /// Checks that, for XRD and every resource created through the test environment, the
+    /// amount held across all vaults equals the total supply.
+    pub fn check_supply_conservation(&self) -> Result<(), String> {
+        for resource in self.known_resources() {
+            let held = self.total_held(resource);
+            let supply = self.total_supply(resource);
+            if held != supply {
+                let name = match self.lookup_name(&resource) {
+                    Some(name) => name,
+                    None => resource.to_string(),
+                };
+                return Err(format!(
+                    "Supply of {} is not conserved: {} held in vaults, total supply is {}",
+                    name, held, supply
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Enables or disables checking supply conservation after every transaction.
+    ///
+    /// When enabled, any transaction after which a resource's vaults don't add up to its
+    /// total supply panics with the violation and the receipt.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to check after every transaction.
+    pub fn check_conservation(&mut self, enabled: bool) -> &mut Self {
+        self.conservation_checks = enabled;
+
+        self
+    }
+}
@@ -0,0 +1,399 @@
+use std::fmt;
+
+use radix_engine::ledger::SubstateStore;
+use radix_engine::model::{Receipt, ValidatedInstruction};
+use scrypto::prelude::*;
+
+use crate::{Contents, TestEnv};
+
+/// The state and holdings of the known components before a transaction.
+pub(crate) type StateCapture = HashMap<Address, (Vec<u8>, HashMap<Address, Contents>)>;
+
+/// What a frame of an execution trace invoked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameCall {
+    /// A blueprint function called by an instruction.
+    Function {
+        package: Address,
+        blueprint: String,
+        function: String,
+    },
+    /// A component method called by an instruction.
+    Method { component: Address, method: String },
+    /// A component not called by any instruction whose state or holdings changed, so it
+    /// was invoked by another blueprint. The method is not known to the engine's receipt.
+    Inferred { component: Address, blueprint: String },
+}
+
+/// A call frame of an execution trace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// What the frame invoked.
+    pub call: FrameCall,
+    /// The call, rendered with registered names.
+    pub label: String,
+    /// The output of the call, if the instruction produced one.
+    pub output: Option<String>,
+    /// The net change of each resource held by the called component over the whole
+    /// transaction, not the buckets passed to or returned by the call.
+    pub resource_changes: Vec<(Address, Decimal)>,
+    /// The frames invoked from within this one.
+    pub children: Vec<Frame>,
+}
+
+impl Frame {
+    /// Returns the component the frame invoked, if it is a method call.
+    pub fn component(&self) -> Option<Address> {
+        match &self.call {
+            FrameCall::Function { .. } => None,
+            FrameCall::Method { component, .. } | FrameCall::Inferred { component, .. } => {
+                Some(*component)
+            }
+        }
+    }
+
+    /// Returns the name of the function or method the frame invoked, if it is known.
+    pub fn name(&self) -> Option<&str> {
+        match &self.call {
+            FrameCall::Function { function, .. } => Some(function),
+            FrameCall::Method { method, .. } => Some(method),
+            FrameCall::Inferred { .. } => None,
+        }
+    }
+
+    fn collect<'f>(&'f self, frames: &mut Vec<&'f Frame>) {
+        frames.push(self);
+        for child in &self.children {
+            child.collect(frames);
+        }
+    }
+
+    fn render(&self, f: &mut fmt::Formatter<'_>, depth: usize, trace: &Trace) -> fmt::Result {
+        let indent = "  ".repeat(depth);
+        write!(f, "{}{}", indent, self.label)?;
+        if let Some(output) = &self.output {
+            write!(f, " => {}", output)?;
+        }
+        writeln!(f)?;
+        for (resource, change) in &self.resource_changes {
+            let sign = if *change > Decimal::zero() { "+" } else { "" };
+            writeln!(f, "{}  ~ {}{} {}", indent, sign, change, trace.name(resource))?;
+        }
+        for child in &self.children {
+            child.render(f, depth + 1, trace)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A state-diff summary of a transaction executed through a `TestEnv`, laid out as call
+/// frames.
+///
+/// Top-level frames are the calls made by the instructions. The radix engine does not report
+/// nested calls, so calls between components are inferred from the components whose state or
+/// holdings changed without being called by an instruction: they become children of the only
+/// call not made to a user account, or top-level frames when there are several.
+///
+/// Being derived from the state before and after the transaction, the summary has limits:
+///
+/// * Nested calls that change neither state nor holdings, such as reading a price from an
+///   oracle, do not appear.
+/// * A component called several times from within blueprints appears as a single frame,
+///   and without the names of the methods called.
+/// * Resource changes are the net change of each component's vaults, not the buckets moved
+///   between calls, so a resource passed through a component leaves no trace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    /// The result, with the error rendered on failure.
+    pub result: Result<(), String>,
+    /// The top-level frames, in instruction order.
+    pub frames: Vec<Frame>,
+    /// The user accounts and the treasury when the transaction ran.
+    pub accounts: Vec<Address>,
+    names: Vec<(Address, String)>,
+}
+
+impl Trace {
+    /// Returns every frame of the trace, depth first.
+    pub fn all_frames(&self) -> Vec<&Frame> {
+        let mut frames = Vec::new();
+        for frame in &self.frames {
+            frame.collect(&mut frames);
+        }
+
+        frames
+    }
+
+    /// Returns the frames invoking the given component, optionally only a given method.
+    ///
+    /// Inferred frames only match when no method is given.
+    pub fn calls(&self, component: Address, method: Option<&str>) -> Vec<&Frame> {
+        self.all_frames()
+            .into_iter()
+            .filter(|frame| frame.component() == Some(component))
+            .filter(|frame| match method {
+                Some(method) => frame.name() == Some(method),
+                None => true,
+            })
+            .collect()
+    }
+
+    /// Returns the components invoked by the transaction, directly or not.
+    pub fn touched(&self) -> Vec<Address> {
+        let mut components = Vec::new();
+        for frame in self.all_frames() {
+            if let Some(component) = frame.component() {
+                if !components.contains(&component) {
+                    components.push(component);
+                }
+            }
+        }
+
+        components
+    }
+
+    /// Returns the registered name of a component or resource, or its address.
+    pub fn name(&self, address: &Address) -> String {
+        match self.names.iter().find(|(a, _)| a == address) {
+            Some((_, name)) => name.clone(),
+            None => address.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.result {
+            Ok(()) => writeln!(f, "State diff trace: Success")?,
+            Err(error) => writeln!(f, "State diff trace: Failure ({})", error)?,
+        }
+        for frame in &self.frames {
+            frame.render(f, 1, self)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, L: SubstateStore> TestEnv<'a, L> {
+    /// Returns the state-diff summary of the last transaction, if traces are captured.
+    ///
+    /// # Examples
+    /// ```
+    /// use scrypto_unit::*;
+    /// use radix_engine::ledger::*;
+    /// use scrypto::prelude::*;
+    ///
+    /// let mut ledger = InMemorySubstateStore::with_bootstrap();
+    /// let mut env = TestEnv::new_with_tracing(&mut ledger);
+    /// env.create_user("acc1");
+    /// env.create_user("acc2");
+    /// let acc2 = *env.get_user("acc2");
+    /// env.acting_as("acc1");
+    /// env.transfer_resource(10.into(), &RADIX_TOKEN, &acc2);
+    ///
+    /// let trace = env.last_trace().unwrap();
+    /// assert!(trace.touched().contains(&acc2.account));
+    /// ```
+    pub fn last_trace(&self) -> Option<&Trace> {
+        self.history.last().and_then(|record| record.trace.as_ref())
+    }
+
+    /// Panics with the rendered receipt, and the trace of the last transaction if it was
+    /// captured, when the given receipt is a failure.
+    ///
+    /// # Arguments
+    ///
+    /// * `receipt` - The receipt of the last transaction.
+    pub fn assert_success(&self, receipt: &Receipt) {
+        if receipt.result.is_err() {
+            panic!("transaction failed\n{}", self.describe_last(receipt));
+        }
+    }
+
+    /// Renders a receipt followed by the trace of the last transaction, if captured.
+    pub(crate) fn describe_last(&self, receipt: &Receipt) -> String {
+        match self.last_trace() {
+            Some(trace) => format!("{}{}", self.display(receipt), trace),
+            None => self.display(receipt).to_string(),
+        }
+    }
+
+    pub(crate) fn capture_state(&self) -> StateCapture {
+        let ledger = self.executor.ledger();
+        self.known_components()
+            .into_iter()
+            .filter_map(|address| {
+                ledger.get_component(address).map(|component| {
+                    (
+                        address,
+                        (component.state().to_vec(), self.get_holdings(address)),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    pub(crate) fn build_trace(&self, receipt: &Receipt, before: &StateCapture) -> Trace {
+        let after = self.capture_state();
+        let mut changes: HashMap<Address, Vec<(Address, Decimal)>> = HashMap::new();
+        let mut changed = Vec::new();
+        for (address, (state, holdings)) in &after {
+            let (state_before, holdings_before) = match before.get(address) {
+                Some((state, holdings)) => (state.clone(), holdings.clone()),
+                None => (Vec::new(), HashMap::new()),
+            };
+            let delta = resource_changes(&holdings_before, holdings);
+            if *state != state_before || !delta.is_empty() {
+                changed.push(*address);
+            }
+            changes.insert(*address, delta);
+        }
+
+        let display = self.display(receipt);
+        let mut frames: Vec<Frame> = Vec::new();
+        for (i, instruction) in receipt.transaction.instructions.iter().enumerate() {
+            let call = match instruction {
+                ValidatedInstruction::CallFunction {
+                    package_address,
+                    blueprint_name,
+                    function,
+                    ..
+                } => FrameCall::Function {
+                    package: *package_address,
+                    blueprint: blueprint_name.clone(),
+                    function: function.clone(),
+                },
+                ValidatedInstruction::CallMethod {
+                    component_address,
+                    method,
+                    ..
+                }
+                | ValidatedInstruction::CallMethodWithAllResources {
+                    component_address,
+                    method,
+                } => FrameCall::Method {
+                    component: *component_address,
+                    method: method.clone(),
+                },
+                _ => continue,
+            };
+            let resource_changes = match &call {
+                FrameCall::Method { component, .. } if !called_before(&frames, *component) => {
+                    changes.get(component).cloned().unwrap_or_default()
+                }
+                _ => Vec::new(),
+            };
+            frames.push(Frame {
+                call,
+                label: display.instruction(instruction),
+                output: receipt
+                    .outputs
+                    .get(i)
+                    .map(|output| format!("{:?}", output)),
+                resource_changes,
+                children: Vec::new(),
+            });
+        }
+
+        let ledger = self.executor.ledger();
+        let mut inferred: Vec<Frame> = changed
+            .into_iter()
+            .filter(|address| !called_before(&frames, *address))
+            .map(|address| {
+                let blueprint = ledger
+                    .get_component(address)
+                    .map(|component| component.blueprint_name().to_owned())
+                    .unwrap_or_default();
+                let label = match self.lookup_name(&address) {
+                    Some(name) => format!("{} ({})", name, blueprint),
+                    None => format!("{} ({})", address, blueprint),
+                };
+                Frame {
+                    call: FrameCall::Inferred {
+                        component: address,
+                        blueprint,
+                    },
+                    label,
+                    output: None,
+                    resource_changes: changes.remove(&address).unwrap_or_default(),
+                    children: Vec::new(),
+                }
+            })
+            .collect();
+        inferred.sort_by(|a, b| a.label.cmp(&b.label));
+
+        let accounts: Vec<Address> = self.users.values().map(|user| user.account).collect();
+        let parents: Vec<usize> = (0..frames.len())
+            .filter(|i| match frames[*i].component() {
+                Some(component) => !accounts.contains(&component),
+                None => true,
+            })
+            .collect();
+        match parents.as_slice() {
+            [parent] => frames[*parent].children = inferred,
+            _ => frames.extend(inferred),
+        }
+
+        let mut trace = Trace {
+            result: receipt
+                .result
+                .as_ref()
+                .map(|_| ())
+                .map_err(|error| format!("{:?}", error)),
+            frames,
+            accounts: self
+                .users
+                .values()
+                .map(|user| user.account)
+                .chain(self.treasury.map(|user| user.account))
+                .collect(),
+            names: Vec::new(),
+        };
+        let mut names = Vec::new();
+        for frame in trace.all_frames() {
+            let resources = frame.resource_changes.iter().map(|(resource, _)| *resource);
+            for address in frame.component().into_iter().chain(resources) {
+                if let Some(name) = self.lookup_name(&address) {
+                    names.push((address, name));
+                }
+            }
+        }
+        trace.names = names;
+
+        trace
+    }
+}
+
+fn called_before(frames: &[Frame], component: Address) -> bool {
+    frames
+        .iter()
+        .any(|frame| frame.component() == Some(component))
+}
+
+fn amount(contents: Option<&Contents>) -> Decimal {
+    match contents {
+        Some(Contents::Amount(amount)) => *amount,
+        Some(Contents::NonFungibleKeys(keys)) => (keys.len() as u64).into(),
+        None => Decimal::zero(),
+    }
+}
+
+fn resource_changes(
+    before: &HashMap<Address, Contents>,
+    after: &HashMap<Address, Contents>,
+) -> Vec<(Address, Decimal)> {
+    let mut resources: Vec<Address> = before.keys().chain(after.keys()).cloned().collect();
+    resources.sort_by_key(|resource| resource.to_string());
+    resources.dedup();
+
+    resources
+        .into_iter()
+        .map(|resource| {
+            let change = amount(after.get(&resource)) - amount(before.get(&resource));
+            (resource, change)
+        })
+        .filter(|(_, change)| *change != Decimal::zero())
+        .collect()
+}
@@ -0,0 +1,143 @@
+use radix_engine::ledger::SubstateStore;
+
+use crate::fixture::Fixture;
+use crate::snapshot::diff;
+use crate::{History, TestEnv};
+
+/// The outcome of a scenario run against one version of a package.
+#[derive(Debug, Clone)]
+pub struct VersionRun {
+    /// The version of the package.
+    pub version: String,
+    /// The normalized state of the test environment after the scenario.
+    pub snapshot: String,
+    /// The transactions executed by the scenario.
+    pub history: History,
+}
+
+/// The outcomes of the same scenario run against several versions of a package.
+#[derive(Debug, Clone)]
+pub struct VersionComparison {
+    /// The runs, in the order the versions were given.
+    pub runs: Vec<VersionRun>,
+}
+
+impl VersionComparison {
+    /// Describes how each version behaved differently from the first one: transactions
+    /// with a different outcome or outputs, and differences in the final state.
+    pub fn differences(&self) -> Vec<String> {
+        let mut differences = Vec::new();
+        let reference = &self.runs[0];
+
+        for run in &self.runs[1..] {
+            let label = format!("{} vs {}", reference.version, run.version);
+
+            if reference.history.len() != run.history.len() {
+                differences.push(format!(
+                    "{}: {} transactions vs {}",
+                    label,
+                    reference.history.len(),
+                    run.history.len()
+                ));
+            }
+            for (expected, actual) in reference.history.iter().zip(run.history.iter()) {
+                if expected.result.is_ok() != actual.result.is_ok() {
+                    differences.push(format!(
+                        "{}: transaction {} {:?} vs {:?}",
+                        label, expected.index, expected.result, actual.result
+                    ));
+                } else if expected.outputs != actual.outputs {
+                    differences.push(format!(
+                        "{}: transaction {} returned different outputs",
+                        label, expected.index
+                    ));
+                }
+            }
+            if reference.snapshot != run.snapshot {
+                differences.push(format!(
+                    "{}: final state differs\n{}",
+                    label,
+                    diff(&reference.snapshot, &run.snapshot)
+                ));
+            }
+        }
+
+        differences
+    }
+
+    /// Panics if any version behaved differently from the first one.
+    pub fn assert_compatible(&self) {
+        let differences = self.differences();
+        if !differences.is_empty() {
+            panic!("Versions are not compatible:\n{}", differences.join("\n"));
+        }
+    }
+}
+
+/// Runs the same scenario against several versions of a package, each in a fresh test
+/// environment where the version is published under the logical package name.
+///
+/// The versioned name is unregistered before taking the snapshot, so the final states only
+/// refer to the package by its logical name and compare equal across versions.
+///
+/// # Arguments
+///
+/// * `ledger` - Creates the fresh ledger of each run.
+/// * `name` - The logical name of the package.
+/// * `versions` - The versions, with their code.
+/// * `scenario` - The scenario, run after publishing the package.
+///
+/// # Examples
+/// ```
+/// use scrypto_unit::*;
+/// use radix_engine::ledger::*;
+/// use scrypto::prelude::*;
+///
+/// let code: &[u8] = include_code!("../tests/assets/hello-world", "hello_world");
+/// let comparison = compare_versions(
+///     InMemorySubstateStore::with_bootstrap,
+///     "hello_world",
+///     &[("v1", code), ("v2", code)],
+///     |env| {
+///         env.create_user("alice");
+///         env.using_package("hello_world");
+///         env.call_function("Hello", "new", vec!["1".to_owned()]);
+///         env.call("update_state", vec!["2".to_owned()]);
+///     },
+/// );
+///
+/// comparison.assert_compatible();
+/// ```
+pub fn compare_versions<L, M, S>(
+    ledger: M,
+    name: &str,
+    versions: &[(&str, &[u8])],
+    scenario: S,
+) -> VersionComparison
+where
+    L: SubstateStore,
+    M: Fn() -> L + 'static,
+    S: Fn(&mut TestEnv<'_, L>) + 'static,
+{
+    assert!(!versions.is_empty(), "No versions to compare");
+    let fixture = Fixture::new(ledger, scenario);
+
+    let runs = versions
+        .iter()
+        .map(|(version, code)| {
+            let mut ledger = fixture.ledger();
+            let mut env = TestEnv::new(&mut ledger);
+            env.publish_package_version(name, version, code);
+            fixture.setup(&mut env);
+            env.packages.remove(&format!("{}@{}", name, version));
+
+            VersionRun {
+                version: String::from(*version),
+                snapshot: env.snapshot(),
+                history: env.history.clone(),
+            }
+        })
+        .collect();
+
+    VersionComparison { runs }
+}
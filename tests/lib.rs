@@ -1,6 +1,7 @@
 extern crate radix_engine;
 
 use radix_engine::ledger::*;
+use scrypto::prelude::*;
 use scrypto_unit::*;
 
 #[test]
@@ -37,3 +38,799 @@ fn test_acting_as() {
     assert_eq!(user.account, test_env.current_user.unwrap().account);
     assert_eq!(user.key, test_env.current_user.unwrap().key);
 }
+
+#[test]
+fn test_display_receipt() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+
+    test_env.create_user("alice");
+    test_env.publish_package(
+        "hello_world",
+        include_code!("./assets/hello-world", "hello_world"),
+    );
+
+    let receipt = test_env.call_function("Hello", "new", vec!["1".to_owned()]);
+    let rendered = test_env.display(&receipt).to_string();
+
+    assert!(rendered.contains("Result: Success"));
+    assert!(rendered.contains("Signers: [alice]"));
+    assert!(rendered.contains("hello_world::Hello::new"));
+    assert!(rendered.contains("alice.deposit_batch"));
+}
+
+#[test]
+fn test_named_components_and_resources() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+
+    let user = test_env.create_user("alice");
+    test_env.publish_package(
+        "hello_world",
+        include_code!("./assets/hello-world", "hello_world"),
+    );
+
+    let receipt = test_env.call_function("Hello", "new", vec!["1".to_owned()]);
+    assert_eq!(test_env.get_component("Hello"), receipt.component(0).unwrap());
+
+    let component = receipt.component(0).unwrap();
+    test_env.register_component("hello", component);
+    assert_eq!(test_env.lookup_name(&component), Some("hello".to_owned()));
+    assert!(!test_env.components.contains_key("Hello"));
+    let receipt = test_env.call_method("hello", "update_state", vec!["2".to_owned()]);
+    assert!(receipt.result.is_ok(), "{}", test_env.display(&receipt));
+
+    let token = test_env.create_token(10000.into());
+    test_env.register_resource("TKN", token.address());
+    assert_eq!(test_env.get_amount_for_rd("alice", "TKN"), 10000.into());
+    assert_eq!(test_env.lookup_name(&token.address()), Some("TKN".to_owned()));
+    assert_eq!(test_env.lookup_name(&user.account), Some("alice".to_owned()));
+}
+
+#[test]
+fn test_using_component() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+
+    test_env.create_user("alice");
+    test_env.publish_package(
+        "hello_world",
+        include_code!("./assets/hello-world", "hello_world"),
+    );
+
+    let first = test_env.call_function("Hello", "new", vec!["1".to_owned()]);
+    let second = test_env.call_function("Hello", "new", vec!["2".to_owned()]);
+    assert_eq!(test_env.get_current_component(), first.component(0).unwrap());
+
+    test_env.register_component("second", second.component(0).unwrap());
+    test_env.using_component("second");
+
+    let mut receipt = test_env.call("update_state", vec!["3".to_owned()]);
+    assert!(receipt.result.is_ok(), "{}", test_env.display(&receipt));
+    let old_state: u32 = return_of_call_method(&mut receipt, "update_state");
+    assert_eq!(old_state, 2);
+}
+
+#[test]
+fn test_as_user_restores_previous_user() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+
+    let alice = test_env.create_user("alice");
+    let admin = test_env.create_user("admin");
+
+    let acting = test_env.as_user("admin", |env| env.get_current_user());
+    assert_eq!(acting, admin);
+    assert_eq!(test_env.get_current_user(), alice);
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        test_env.as_user("admin", |_| panic!("failure while acting as admin"))
+    }));
+    assert!(result.is_err());
+    assert_eq!(test_env.get_current_user(), alice);
+}
+
+#[test]
+fn test_user_keys_are_deterministic() {
+    let mut ledger1 = InMemorySubstateStore::with_bootstrap();
+    let mut env1 = TestEnv::new(&mut ledger1);
+    env1.create_user("alice");
+    let bob1 = env1.create_user("bob");
+
+    let mut ledger2 = InMemorySubstateStore::with_bootstrap();
+    let mut env2 = TestEnv::new(&mut ledger2);
+    let bob2 = env2.create_user("bob");
+
+    // Only keys are deterministic: account addresses are derived from the ledger nonce, a
+    // known limitation, so they are not compared.
+    assert_eq!(bob1.key, bob2.key);
+    assert_ne!(
+        env2.create_user_with_seed("carol", 1).key,
+        env2.create_user_with_seed("carol", 2).key
+    );
+}
+
+#[test]
+fn test_fund_with_registered_resource() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+
+    test_env.create_user("alice");
+    let bob = test_env.create_user_with_balance("bob", 250.into());
+    assert_eq!(test_env.get_amount_for_rd(bob.account, RADIX_TOKEN), 250.into());
+
+    let token = test_env.create_token(10000.into());
+    test_env.register_resource("USDC", token.address());
+    let treasury = test_env.treasury();
+    test_env.transfer_resource(4000.into(), "USDC", &treasury);
+
+    let receipt = test_env.fund("bob", "USDC", 1500.into());
+    assert!(receipt.result.is_ok(), "{}", test_env.display(&receipt));
+    assert_eq!(test_env.get_amount_for_rd("bob", "USDC"), 1500.into());
+    assert_eq!(test_env.get_amount_for_rd(treasury.account, "USDC"), 2500.into());
+}
+
+#[test]
+fn test_epoch_control() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+
+    test_env.set_epoch(100);
+    assert_eq!(test_env.current_epoch(), 100);
+
+    test_env.advance_epochs(3).advance_epochs(2);
+    assert_eq!(test_env.current_epoch(), 105);
+}
+
+#[test]
+fn test_transaction_history() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+
+    test_env.create_user("alice");
+    test_env.publish_package(
+        "hello_world",
+        include_code!("./assets/hello-world", "hello_world"),
+    );
+    let receipt = test_env.call_function("Hello", "new", vec!["1".to_owned()]);
+    let component = receipt.component(0).unwrap();
+    test_env.call_method(&component, "update_state", vec!["2".to_owned()]);
+    let bob = test_env.create_user("bob");
+    test_env.transfer_resource(1000000000.into(), RADIX_TOKEN, &bob);
+
+    let history = &test_env.history;
+    assert_eq!(history.of_kind(TransactionKind::CreateUser).len(), 2);
+    assert_eq!(history.of_kind(TransactionKind::PublishPackage).len(), 1);
+    assert_eq!(history.calls_to(component).len(), 1);
+    assert_eq!(history.failed().len(), 1);
+    assert_eq!(history.by_user("alice").len(), 3);
+    assert!(!history.last().unwrap().is_success());
+}
+
+#[test]
+fn test_execution_trace() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new_with_tracing(&mut ledger);
+
+    test_env.create_user("alice");
+    test_env.publish_package(
+        "hello_world",
+        include_code!("./assets/hello-world", "hello_world"),
+    );
+    test_env.call_function("Hello", "new", vec!["1".to_owned()]);
+    let receipt = test_env.call("update_state", vec!["2".to_owned()]);
+    test_env.assert_success(&receipt);
+
+    let component = test_env.get_component("Hello");
+    let trace = test_env.last_trace().unwrap();
+    assert_eq!(trace.calls(component, Some("update_state")).len(), 1);
+    assert!(trace.to_string().contains("CALL_METHOD Hello.update_state"));
+
+    let bob = test_env.create_user("bob");
+    test_env.transfer_resource(1000000000.into(), RADIX_TOKEN, &bob);
+    assert!(test_env.last_trace().unwrap().result.is_err());
+}
+
+#[test]
+fn test_call_assertions() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new_with_tracing(&mut ledger);
+
+    test_env.create_user("alice");
+    test_env.publish_package(
+        "hello_world",
+        include_code!("./assets/hello-world", "hello_world"),
+    );
+    test_env.call_function("Hello", "new", vec!["1".to_owned()]);
+    test_env.call("update_state", vec!["2".to_owned()]);
+
+    assert_called!(test_env, "Hello", "update_state", 1);
+    assert_not_called!(test_env, "Hello", "new");
+    assert_only_called!(test_env, "Hello");
+    assert_eq!(test_env.call_count("Hello", None), 1);
+    assert!(test_env.check_called("Hello", Some("update_state"), Some(2)).is_err());
+
+    let hello = test_env.get_component("Hello");
+    let record = test_env.history.last().unwrap();
+    assert_called!(record, hello, "update_state", 1);
+    assert_only_called!(record.trace.as_ref().unwrap(), hello);
+}
+
+#[test]
+fn test_export_and_replay_session() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+
+    test_env.create_user("alice");
+    test_env.publish_package(
+        "hello_world",
+        include_code!("./assets/hello-world", "hello_world"),
+    );
+    test_env.call_function("Hello", "new", vec!["1".to_owned()]);
+    test_env.call("update_state", vec!["2".to_owned()]);
+    let treasury = test_env.treasury();
+
+    let path = std::env::temp_dir().join("scrypto-unit-session.sbor");
+    test_env.export_session().save(&path).unwrap();
+    let session = Session::load(&path).unwrap();
+
+    let mut replay_ledger = InMemorySubstateStore::with_bootstrap();
+    let mut replay_env = TestEnv::new(&mut replay_ledger);
+    replay_env.replay_session(&session).unwrap();
+
+    assert_eq!(replay_env.get_user("alice"), test_env.get_user("alice"));
+    assert_eq!(
+        replay_env.get_component("Hello"),
+        test_env.get_component("Hello")
+    );
+    assert_eq!(replay_env.history.len(), test_env.history.len());
+
+    let mut receipt = replay_env.call("update_state", vec!["3".to_owned()]);
+    let old_state: u32 = return_of_call_method(&mut receipt, "update_state");
+    assert_eq!(old_state, 2);
+    let receipt = replay_env.call_function("Hello", "new", vec!["4".to_owned()]);
+    assert!(receipt.result.is_ok(), "{}", replay_env.display(&receipt));
+    assert_eq!(replay_env.treasury(), treasury);
+}
+
+#[test]
+fn test_run_manifest_file() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+
+    test_env.create_user("alice");
+    test_env.publish_package(
+        "hello_world",
+        include_code!("./assets/hello-world", "hello_world"),
+    );
+
+    let receipt = test_env.run_manifest_file("tests/assets/manifests/new_hello.rtm");
+    assert!(receipt.result.is_ok(), "{}", test_env.display(&receipt));
+    assert_eq!(
+        test_env.history.last().unwrap().kind,
+        TransactionKind::Manifest
+    );
+}
+
+#[test]
+fn test_snapshot() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+
+    test_env.create_user("alice");
+    test_env.publish_package(
+        "hello_world",
+        include_code!("./assets/hello-world", "hello_world"),
+    );
+    test_env.call_function("Hello", "new", vec!["1".to_owned()]);
+
+    let snapshot = test_env.snapshot();
+    assert!(snapshot.contains("[users]\nalice\n"));
+    assert!(snapshot.contains("Hello = "));
+    assert!(snapshot.contains("created: Hello"));
+
+    assert!(!snapshot.contains(&test_env.get_component("Hello").to_string()));
+
+    let dir = std::env::temp_dir().join("scrypto-unit-snapshots");
+    let _ = std::fs::remove_dir_all(&dir);
+    let mode = SnapshotMode::default();
+    check_snapshot_in(&dir, "after_new", &snapshot, mode).unwrap();
+    assert_eq!(std::fs::read_to_string(dir.join("after_new.snap")).unwrap(), snapshot);
+    check_snapshot_in(&dir, "after_new", &test_env.snapshot(), mode).unwrap();
+
+    test_env.call("update_state", vec!["2".to_owned()]);
+    let error = check_snapshot_in(&dir, "after_new", &test_env.snapshot(), mode).unwrap_err();
+    assert!(error.contains("does not match"), "{}", error);
+    assert!(error.contains(" - ") && error.contains(" + "), "{}", error);
+
+    let bless = SnapshotMode {
+        bless: true,
+        ci: true,
+    };
+    check_snapshot_in(&dir, "after_new", &test_env.snapshot(), bless).unwrap();
+    check_snapshot_in(&dir, "after_new", &test_env.snapshot(), mode).unwrap();
+}
+
+#[test]
+fn test_snapshot_missing_in_ci() {
+    let dir = std::env::temp_dir().join("scrypto-unit-snapshots-ci");
+    let _ = std::fs::remove_dir_all(&dir);
+    let ci = SnapshotMode {
+        bless: false,
+        ci: true,
+    };
+
+    let error = check_snapshot_in(&dir, "missing", "[users]\n", ci).unwrap_err();
+    assert!(error.contains("is missing"), "{}", error);
+    assert!(!dir.join("missing.snap").exists());
+}
+
+#[test]
+#[should_panic(expected = "does not match")]
+fn test_snapshot_mismatch() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+    test_env.create_user("alice");
+
+    let dir = std::env::temp_dir().join("scrypto-unit-snapshots-mismatch");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("alice.snap"), "[users]\nbob\n").unwrap();
+    assert_snapshot!(test_env, "alice", dir);
+}
+
+#[test]
+fn test_snapshot_macro() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+
+    test_env.create_user("alice");
+    test_env.publish_package(
+        "hello_world",
+        include_code!("./assets/hello-world", "hello_world"),
+    );
+    test_env.call_function("Hello", "new", vec!["1".to_owned()]);
+
+    let dir = snapshot_dir(env!("CARGO_MANIFEST_DIR"), file!());
+    assert_eq!(dir, std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots"));
+
+    let dir = std::env::temp_dir().join("scrypto-unit-snapshots-macro");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("hello_new.snap"), test_env.snapshot()).unwrap();
+    assert_snapshot!(test_env, "hello_new", dir);
+}
+
+#[test]
+fn test_supply_conservation() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+    test_env.check_conservation(true);
+
+    test_env.create_user("alice");
+    let bob = test_env.create_user("bob");
+    let token = test_env.create_token(10000.into());
+    test_env.transfer_resource(2500.into(), &token, &bob);
+
+    assert_eq!(test_env.total_held(&token), 10000.into());
+    assert_eq!(test_env.total_held(RADIX_TOKEN), test_env.total_supply(RADIX_TOKEN));
+    assert!(test_env.check_supply_conservation().is_ok());
+}
+
+#[test]
+fn test_invariants() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+
+    test_env.create_user("alice");
+    test_env.publish_package(
+        "hello_world",
+        include_code!("./assets/hello-world", "hello_world"),
+    );
+    test_env.call_function("Hello", "new", vec!["1".to_owned()]);
+
+    let mut last_state = 0;
+    test_env.add_invariant("state_non_decreasing", move |env| {
+        let state = match env.history.last() {
+            Some(record) if record.calls_named("update_state") => {
+                scrypto_decode::<u32>(&record.outputs[0]).unwrap()
+            }
+            _ => return Ok(()),
+        };
+        if state < last_state {
+            return Err(format!("state went from {} to {}", last_state, state));
+        }
+        last_state = state;
+        Ok(())
+    });
+
+    test_env.call("update_state", vec!["5".to_owned()]);
+    test_env.call("update_state", vec!["3".to_owned()]);
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        test_env.call("update_state", vec!["4".to_owned()]);
+    }));
+    assert!(result.is_err());
+
+    test_env.remove_invariant("state_non_decreasing");
+    assert!(test_env.check_invariants().is_ok());
+}
+
+#[test]
+fn test_scenario_shrinks_failures() {
+    let mut scenario = Scenario::new(InMemorySubstateStore::with_bootstrap, |env| {
+        env.create_user("alice");
+    });
+    scenario.action("noop", |_, _| Ok(()));
+    scenario.action("advance", |env, rng| {
+        env.advance_epochs(rng.below(3) + 1);
+        Ok(())
+    });
+    scenario.action("epoch_limit", |env, _| match env.current_epoch() > 4 {
+        true => Err(format!("epoch {} is past the limit", env.current_epoch())),
+        false => Ok(()),
+    });
+
+    let failure = (0..20)
+        .find_map(|seed| scenario.run(seed, 30).err())
+        .expect("no seed failed");
+
+    assert_eq!(failure.steps.last().unwrap().0, "epoch_limit");
+    assert!(failure
+        .steps
+        .iter()
+        .all(|(name, _)| name == "advance" || name == "epoch_limit"));
+    assert!(scenario.run(failure.seed, 30).is_err());
+}
+
+#[test]
+fn test_fuzz_package_is_reproducible() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+
+    test_env.create_user("alice");
+    test_env.publish_package(
+        "hello_world",
+        include_code!("./assets/hello-world", "hello_world"),
+    );
+    test_env.call_function("Hello", "new", vec!["1".to_owned()]);
+
+    let first = test_env.fuzz_package("hello_world", &["Hello"], 5, 7);
+    let second = test_env.fuzz_package("hello_world", &["Hello"], 5, 7);
+
+    assert!(first.flagged().is_empty(), "{}", first);
+    assert!(first.skipped().is_empty(), "{}", first);
+    assert!(first.cases.iter().any(|case| case.target == "Hello.update_state"));
+    let args = |report: &FuzzReport| -> Vec<Vec<String>> {
+        report.cases.iter().map(|case| case.args.clone()).collect()
+    };
+    assert_eq!(args(&first), args(&second));
+}
+
+#[test]
+#[cfg(feature = "blueprints")]
+fn test_fuzz_bucket_arguments() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+
+    test_env.create_user("alice");
+    test_env.create_echo();
+
+    let report = test_env.fuzz_package(BLUEPRINTS_PACKAGE, &["Echo"], 10, 3);
+    assert!(report.flagged().is_empty(), "{}", report);
+    assert!(report.cases.iter().any(|case| case.target.ends_with(".sink")));
+}
+
+#[test]
+fn test_execution_metrics() {
+    let mut ledger = MeteredSubstateStore::new(InMemorySubstateStore::with_bootstrap());
+    let mut test_env = TestEnv::new_metered(&mut ledger);
+
+    test_env.create_user("alice");
+    test_env.publish_package(
+        "hello_world",
+        include_code!("./assets/hello-world", "hello_world"),
+    );
+    test_env.call_function("Hello", "new", vec!["1".to_owned()]);
+
+    let (receipt, metrics) =
+        test_env.measure(|env| env.call("update_state", vec!["2".to_owned()]));
+    assert!(receipt.result.is_ok());
+    assert_eq!(metrics.transactions, 1);
+    assert_eq!(metrics, test_env.last_metrics());
+    assert!(metrics.substate_writes.unwrap() > 0);
+    assert_cost_below!(metrics, Budget::new().manifest_instructions(2).substate_writes(1000));
+    assert_cost_below!(test_env.history.last().unwrap(), Budget::new().substate_writes(1000));
+
+    let exceeded = metrics.check(&Budget::new().substate_writes(0));
+    assert!(exceeded.is_err());
+}
+
+#[test]
+fn test_bench_compare() {
+    fn setup(env: &mut TestEnv<MeteredSubstateStore<InMemorySubstateStore>>) {
+        env.create_user("alice");
+        env.publish_package(
+            "hello_world",
+            include_code!("./assets/hello-world", "hello_world"),
+        );
+        env.call_function("Hello", "new", vec!["1".to_owned()]);
+    }
+    let baseline = Bench::new(InMemorySubstateStore::with_bootstrap, setup);
+    let candidate = Bench::new(InMemorySubstateStore::with_bootstrap, setup);
+
+    let comparison = baseline.compare(&candidate, 5, |env| {
+        let receipt = env.call("update_state", vec!["2".to_owned()]);
+        assert!(receipt.result.is_ok());
+    });
+
+    assert_eq!(comparison.baseline.samples.len(), 5);
+    assert_eq!(
+        comparison.baseline.cost().substate_writes,
+        comparison.candidate.cost().substate_writes
+    );
+    assert!(comparison.baseline.min() <= comparison.baseline.max());
+}
+
+#[test]
+fn test_package_versions() {
+    let code: &[u8] = include_code!("./assets/hello-world", "hello_world");
+    let comparison = compare_versions(
+        InMemorySubstateStore::with_bootstrap,
+        "hello_world",
+        &[("v1", code), ("v2", code)],
+        |env| {
+            env.create_user("alice");
+            env.call_function("Hello", "new", vec!["1".to_owned()]);
+            env.call("update_state", vec!["2".to_owned()]);
+        },
+    );
+
+    assert_eq!(comparison.runs.len(), 2);
+    assert!(comparison.differences().is_empty());
+
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+    test_env.publish_package_version("hello_world", "v1", code);
+    test_env.publish_package_version("hello_world", "v2", code);
+    assert_ne!(
+        test_env.get_package("hello_world@v1"),
+        test_env.get_package("hello_world@v2")
+    );
+    assert_eq!(
+        test_env.get_current_package(),
+        test_env.get_package("hello_world@v2")
+    );
+    assert_eq!(test_env.get_package_versions("hello_world"), vec!["v1", "v2"]);
+}
+
+#[test]
+#[should_panic(expected = "already published without a version")]
+fn test_publish_package_version_after_plain_package() {
+    let code: &[u8] = include_code!("./assets/hello-world", "hello_world");
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+
+    test_env.publish_package("hello_world", code);
+    test_env.publish_package_version("hello_world", "v2", code);
+}
+
+#[test]
+fn test_differential_identical_implementations() {
+    let code: &[u8] = include_code!("./assets/hello-world", "hello_world");
+    let mut differential = Differential::new(
+        InMemorySubstateStore::with_bootstrap,
+        "hello_world",
+        code,
+        code,
+        |env| {
+            env.create_user("alice");
+        },
+    );
+    differential.step("new", |env| {
+        env.call_function("Hello", "new", vec!["1".to_owned()])
+    });
+    differential.step("update", |env| {
+        env.call("update_state", vec!["2".to_owned()])
+    });
+    assert!(differential.run().is_ok());
+}
+
+#[test]
+fn test_method_coverage() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+
+    test_env.create_user("alice");
+    test_env.publish_package(
+        "hello_world",
+        include_code!("./assets/hello-world", "hello_world"),
+    );
+    test_env.call_function("Hello", "new", vec!["1".to_owned()]);
+    test_env.call("update_state", vec!["2".to_owned()]);
+    test_env.call("update_state", vec!["3".to_owned()]);
+
+    let coverage = test_env.coverage();
+    assert!(coverage.uncovered().is_empty(), "{}", coverage);
+    let key = (
+        "hello_world".to_owned(),
+        "Hello".to_owned(),
+        "update_state".to_owned(),
+    );
+    assert_eq!(coverage.entries.get(&key), Some(&(2, 0)));
+
+    let dir = std::env::temp_dir().join("scrypto-unit-coverage-test");
+    let _ = std::fs::remove_dir_all(&dir);
+    coverage.write_to_dir(&dir).unwrap();
+    coverage.write_to_dir(&dir).unwrap();
+    let collected = Coverage::collect(&dir).unwrap();
+    assert_eq!(collected.entries.get(&key), Some(&(4, 0)));
+}
+
+#[test]
+#[cfg(feature = "blueprints")]
+fn test_nested_call_assertions() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new_with_tracing(&mut ledger);
+
+    test_env.create_user("alice");
+    let oracle = test_env.create_oracle(10.into());
+    let relay = test_env.create_relay();
+
+    // A read-only nested call leaves no trace
+    test_env.call_method(
+        relay,
+        "get_decimal",
+        vec![oracle.to_string(), "get_price".to_owned()],
+    );
+    assert_called!(test_env, relay, "get_decimal", 1);
+    assert_eq!(test_env.call_count(oracle, None), 0);
+    let error = test_env.check_called(oracle, None, Some(0)).unwrap_err();
+    assert!(error.contains("cannot be observed"), "{}", error);
+
+    // A nested call changing state is seen, but not its method
+    let pool = test_env.create_mock("pool");
+    test_env.mock_response(pool, "swap", Decimal::from(1));
+    test_env.call_method(relay, "get_decimal", vec![pool.to_string(), "swap".to_owned()]);
+    assert_called!(test_env, pool);
+    let error = test_env.check_called(pool, Some("swap"), None).unwrap_err();
+    assert!(error.contains("method of nested call unknown"), "{}", error);
+    assert!(test_env.check_called(pool, Some("swap"), Some(0)).is_err());
+    assert!(test_env.check_called(pool, None, Some(0)).is_err());
+}
+
+#[test]
+#[cfg(feature = "blueprints")]
+fn test_mock_component() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+
+    test_env.create_user("alice");
+    let oracle = test_env.create_mock("oracle");
+    let relay = test_env.create_relay();
+    test_env.mock_response("oracle", "get_price", Decimal::from(42));
+    test_env.mock_response_for(
+        "oracle",
+        "get_price",
+        vec![scrypto_encode(&Decimal::from(2))],
+        Decimal::from(84),
+    );
+    test_env.mock_response_with("oracle", "get_epoch", |env| {
+        Decimal::from(env.current_epoch())
+    });
+    test_env.advance_epochs(1);
+
+    assert_eq!(test_env.get_component("oracle"), oracle);
+    assert_eq!(test_env.get_current_component(), oracle);
+    assert_eq!(test_env.get_package(BLUEPRINTS_PACKAGE), test_env.blueprints_package());
+    assert!(test_env.mock_calls(oracle).is_empty());
+
+    let mut receipt = test_env.call_method(
+        relay,
+        "get_decimal",
+        vec![oracle.to_string(), "get_price".to_owned()],
+    );
+    let price: Decimal = return_of_call_method(&mut receipt, "get_decimal");
+    assert_eq!(price, Decimal::from(42));
+
+    let mut receipt = test_env.call_method(
+        relay,
+        "get_decimal_for",
+        vec![oracle.to_string(), "get_price".to_owned(), "2".to_owned()],
+    );
+    let price: Decimal = return_of_call_method(&mut receipt, "get_decimal_for");
+    assert_eq!(price, Decimal::from(84));
+
+    let (mut receipt, metrics) = test_env.measure(|env| {
+        env.call_method(
+            relay,
+            "get_decimal",
+            vec![oracle.to_string(), "get_epoch".to_owned()],
+        )
+    });
+    let epoch: Decimal = return_of_call_method(&mut receipt, "get_decimal");
+    assert_eq!(epoch, Decimal::from(test_env.current_epoch()));
+    assert_eq!(metrics.transactions, 1);
+
+    let refreshes = test_env
+        .history
+        .iter()
+        .filter(|record| record.kind == TransactionKind::MockResponse)
+        .count();
+    test_env.advance_epochs(1);
+    test_env.call_method(relay, "get_decimal", vec![oracle.to_string(), "get_epoch".to_owned()]);
+    let kinds: Vec<TransactionKind> = test_env.history.iter().map(|record| record.kind).collect();
+    assert_eq!(
+        kinds.iter().filter(|kind| **kind == TransactionKind::MockResponse).count(),
+        refreshes + 1
+    );
+    assert_eq!(kinds.last(), Some(&TransactionKind::CallMethod));
+
+    let calls = test_env.mock_calls(oracle);
+    assert_eq!(calls.len(), 4);
+    assert_eq!(calls[0].method, "get_price");
+    assert!(calls[0].args.is_empty());
+    assert_eq!(calls[1].arg::<Decimal>(0), Decimal::from(2));
+    assert_eq!(calls[2].method, "get_epoch");
+}
+
+#[test]
+#[cfg(feature = "blueprints")]
+fn test_fixture_blueprints() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut test_env = TestEnv::new(&mut ledger);
+
+    test_env.create_user("alice");
+    let faucet = test_env.create_faucet("GUM", 1000.into());
+    test_env.call_method(faucet, "free", vec!["10".to_owned()]);
+    assert_eq!(test_env.get_amount_for_rd("alice", "GUM"), 10.into());
+
+    let gum = test_env.get_resource("GUM");
+    let minter = test_env.create_minter("MNT", false);
+    let receipt = test_env.call_method(minter, "mint", vec!["5".to_owned()]);
+    assert!(receipt.result.is_err());
+
+    let mnt = test_env.get_resource("MNT");
+    let badge = *test_env
+        .get_holdings("alice")
+        .keys()
+        .find(|resource| **resource != RADIX_TOKEN && **resource != gum)
+        .unwrap();
+    let receipt = test_env.call_method(
+        minter,
+        "mint_as_admin",
+        vec!["5".to_owned(), format!("1,{}", badge)],
+    );
+    assert!(receipt.result.is_ok());
+    assert_eq!(test_env.get_amount_for_rd("alice", mnt), 5.into());
+    assert_eq!(test_env.get_amount_for_rd("alice", badge), 1.into());
+
+    test_env.call_method(minter, "burn", vec![format!("2,{}", mnt)]);
+    assert_eq!(test_env.get_amount_for_rd("alice", mnt), 3.into());
+    assert_eq!(test_env.total_supply(mnt), 3.into());
+
+    let nft_minter = test_env.create_nft_minter("Cards");
+    test_env.call_method(nft_minter, "mint", vec!["first".to_owned()]);
+    test_env.call_method(nft_minter, "mint", vec!["second".to_owned()]);
+    let nft = *test_env
+        .get_holdings("alice")
+        .iter()
+        .find(|(_, contents)| matches!(contents, Contents::NonFungibleKeys(_)))
+        .unwrap()
+        .0;
+    assert_eq!(
+        test_env.get_non_fungible_keys_for_rd("alice", nft),
+        vec![NonFungibleKey::from(1u128), NonFungibleKey::from(2u128)]
+    );
+
+    let echo = test_env.create_echo();
+    let receipt = test_env.call_method(echo, "echo", vec![format!("10,{}", gum)]);
+    assert!(receipt.result.is_ok());
+    assert_eq!(test_env.get_amount_for_rd("alice", gum), 10.into());
+    test_env.call_method(echo, "sink", vec![format!("4,{}", gum)]);
+    assert_eq!(test_env.get_amount_for_rd("alice", gum), 6.into());
+
+    let oracle = test_env.create_oracle(10.into());
+    test_env.set_oracle_price(oracle, 12.into());
+    let mut receipt = test_env.call_method(oracle, "get_price", Vec::new());
+    let price: Decimal = return_of_call_method(&mut receipt, "get_price");
+    assert_eq!(price, 12.into());
+}